@@ -0,0 +1,333 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Send log records to the journal from a background thread.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use log::{Level, Log, Metadata, Record, SetLoggerError};
+
+use crate::sync::{lock_ignoring_poison, wait_ignoring_poison};
+use crate::{EncodeMode, Error, JournalLog};
+
+/// The state of the queue of pending records.
+#[derive(Default)]
+struct QueueState {
+    /// The number of records queued but not yet sent.
+    pending: usize,
+    /// Whether the background thread has exited.
+    closed: bool,
+    /// Tasks waiting for the queue to drain, by the id of their flush.
+    wakers: Vec<(u64, Waker)>,
+    /// The id of the next flush.
+    next_flush: u64,
+}
+
+/// A queue state shared between the logger and its background thread.
+#[derive(Default)]
+struct SharedQueueState {
+    state: Mutex<QueueState>,
+    drained: Condvar,
+}
+
+impl SharedQueueState {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        lock_ignoring_poison(&self.state)
+    }
+
+    /// Mark one record as sent, and wake up all flushes if the queue is empty.
+    fn record_sent(&self) {
+        let mut state = self.lock();
+        state.pending -= 1;
+        if state.pending == 0 {
+            self.wake_all(&mut state);
+        }
+    }
+
+    fn wake_all(&self, state: &mut QueueState) {
+        for (_, waker) in state.wakers.drain(..) {
+            waker.wake();
+        }
+        self.drained.notify_all();
+    }
+}
+
+/// Marks the queue closed when the background thread exits, even if it panics.
+struct CloseOnDrop(Arc<SharedQueueState>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.closed = true;
+        self.0.wake_all(&mut state);
+    }
+}
+
+/// An encoded record waiting to be sent.
+struct QueuedEntry {
+    /// The level of the record, to route it to a namespace.
+    level: Level,
+    /// The encoded payload of the record.
+    payload: Vec<u8>,
+    /// The record as syslog message, if the syslog fallback is enabled.
+    syslog_message: Option<Vec<u8>>,
+}
+
+fn send_queued_records(
+    logger: Arc<JournalLog>,
+    queue: Receiver<QueuedEntry>,
+    state: Arc<SharedQueueState>,
+) {
+    let _close = CloseOnDrop(state.clone());
+    for entry in queue {
+        let QueuedEntry {
+            level,
+            payload,
+            syslog_message,
+        } = entry;
+        // Like JournalLog we discard errors, because we can't reasonably handle them here.
+        let _ = logger.deliver(level, &payload, || syslog_message.unwrap_or_default());
+        state.record_sent();
+    }
+}
+
+/// A systemd journal logger which sends records from a background thread.
+///
+/// This logger formats log records on the calling thread like [`JournalLog`],
+/// but then puts the formatted entries into a bounded queue, and sends them
+/// to journald from a separate background thread.  As such log calls never
+/// block on the journal socket.
+///
+/// Otherwise it behaves like the underlying [`JournalLog`]: it writes records
+/// to all tees of the logger on the calling thread, and the background thread
+/// routes entries to namespaces, sends them through the custom transport if
+/// any, and falls back to syslog if enabled.
+///
+/// If the queue is full the logger discards new log records until the
+/// background thread catches up.
+///
+/// The background thread doesn't batch records; it sends every record as
+/// soon as it takes it off the queue.  Records thus reach the journal
+/// promptly even without explicit flushes; [`Log::flush`] and
+/// [`Self::flush_async`] only wait for the queue to drain.
+///
+/// Requires the `async` feature.
+pub struct AsyncJournalLog {
+    logger: Arc<JournalLog>,
+    queue: SyncSender<QueuedEntry>,
+    state: Arc<SharedQueueState>,
+}
+
+impl AsyncJournalLog {
+    /// Send records of `logger` from a background thread.
+    ///
+    /// `capacity` denotes the maximum number of records waiting to be sent.
+    ///
+    /// Spawn the background thread, and return an error if spawning the thread
+    /// failed.
+    pub fn new(logger: JournalLog, capacity: usize) -> Result<Self, Error> {
+        let logger = Arc::new(logger);
+        let state = Arc::new(SharedQueueState::default());
+        let (queue, receiver) = sync_channel(capacity);
+        {
+            let logger = logger.clone();
+            let state = state.clone();
+            std::thread::Builder::new()
+                .name("systemd-journal-logger".to_string())
+                .spawn(move || send_queued_records(logger, receiver, state))?;
+        }
+        Ok(Self {
+            logger,
+            queue,
+            state,
+        })
+    }
+
+    /// Install this logger globally.
+    ///
+    /// See [`log::set_boxed_logger`].
+    pub fn install(self) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))
+    }
+
+    /// Get diagnostic information about this logger.
+    ///
+    /// See [`JournalLog::diagnostics`]; records discarded because the queue
+    /// was full count as dropped.
+    pub fn diagnostics(&self) -> crate::JournalDiagnostics {
+        self.logger.diagnostics()
+    }
+
+    /// Wait until all queued records were sent to the journal.
+    ///
+    /// Return a future which resolves once the background thread sent all
+    /// records queued at the time of polling.  The future does not block the
+    /// executor.
+    ///
+    /// # Errors
+    ///
+    /// Fail if the background thread exited before sending all queued records.
+    pub fn flush_async(&self) -> impl Future<Output = Result<(), Error>> {
+        let id = {
+            let mut state = self.state.lock();
+            state.next_flush += 1;
+            state.next_flush
+        };
+        FlushQueue {
+            state: self.state.clone(),
+            id,
+        }
+    }
+
+    /// Encode `record` and put it into the queue, or count it as dropped if the queue is full.
+    fn queue(&self, record: &Record) {
+        let encoded = self.logger.encode_record(record, &[], EncodeMode::Encode);
+        self.logger.report_kv_errors(encoded.kv_errors);
+        let payload = encoded.payload;
+        self.logger.remember(record, &payload);
+        let entry = QueuedEntry {
+            level: record.level(),
+            payload,
+            syslog_message: self
+                .logger
+                .syslog_fallback
+                .then(|| self.logger.syslog_message(record)),
+        };
+        let mut state = self.state.lock();
+        if !state.closed && self.queue.try_send(entry).is_ok() {
+            state.pending += 1;
+        } else {
+            self.logger.client.count_dropped();
+        }
+    }
+}
+
+/// A future which resolves once the queue is drained.
+struct FlushQueue {
+    state: Arc<SharedQueueState>,
+    /// The id of this flush, to keep at most one waker per flush.
+    id: u64,
+}
+
+impl Future for FlushQueue {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock();
+        if state.pending == 0 {
+            Poll::Ready(Ok(()))
+        } else if state.closed {
+            Poll::Ready(Err(Error::Io(worker_exited())))
+        } else {
+            match state.wakers.iter_mut().find(|(id, _)| *id == self.id) {
+                Some((_, waker)) if waker.will_wake(cx.waker()) => {}
+                Some((_, waker)) => *waker = cx.waker().clone(),
+                None => state.wakers.push((self.id, cx.waker().clone())),
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for FlushQueue {
+    fn drop(&mut self) {
+        self.state.lock().wakers.retain(|(id, _)| *id != self.id);
+    }
+}
+
+fn worker_exited() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "the journal background thread exited",
+    )
+}
+
+/// The [`Log`] interface for [`AsyncJournalLog`].
+impl Log for AsyncJournalLog {
+    /// Whether this logger is enabled.
+    ///
+    /// See [`JournalLog::would_log`].
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.would_log(metadata.level(), metadata.target())
+    }
+
+    /// Queue the given `record` for sending to the systemd journal.
+    ///
+    /// Also write the `record` to all tees of the underlying logger, see
+    /// [`JournalLog::log`](Log::log).
+    ///
+    /// Discard the record if this logger isn't enabled for it, if the queue is
+    /// full, if the empty message policy of the underlying logger discards it,
+    /// or if it has colliding keys and the collision policy of the underlying
+    /// logger is [`crate::CollisionPolicy::Error`].
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) || self.logger.skips(record) {
+            return;
+        }
+        if self.logger.check_collisions(record).is_ok() {
+            self.queue(record);
+        }
+        self.logger.tee(record);
+    }
+
+    /// Block until all queued records were sent.
+    fn flush(&self) {
+        let mut state = self.state.lock();
+        while state.pending != 0 && !state.closed {
+            state = wait_ignoring_poison(&self.state.drained, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    use similar_asserts::assert_eq;
+
+    use super::{FlushQueue, SharedQueueState};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn flush_queue_keeps_one_waker() {
+        let state = Arc::new(SharedQueueState::default());
+        state.lock().pending = 1;
+        let mut flush = FlushQueue {
+            state: state.clone(),
+            id: 1,
+        };
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let other = Waker::from(Arc::new(NoopWaker));
+        for waker in [&waker, &waker, &other] {
+            let poll = Pin::new(&mut flush).poll(&mut Context::from_waker(waker));
+            assert!(poll.is_pending());
+        }
+        assert_eq!(state.lock().wakers.len(), 1);
+        assert!(state.lock().wakers[0].1.will_wake(&other));
+
+        drop(flush);
+        assert_eq!(state.lock().wakers.len(), 0);
+        state.lock().pending = 0;
+        let flush = FlushQueue { state, id: 2 };
+        let mut flush = std::pin::pin!(flush);
+        let poll = flush.as_mut().poll(&mut Context::from_waker(&waker));
+        assert!(matches!(poll, Poll::Ready(Ok(()))));
+    }
+}
@@ -11,40 +11,361 @@
 use std::io::prelude::*;
 use std::os::fd::AsFd;
 use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use crate::retry::RetryPolicy;
 use crate::{memfd, socket};
 
-const JOURNALD_PATH: &str = "/run/systemd/journal/socket";
+pub const JOURNALD_PATH: &str = "/run/systemd/journal/socket";
 
-pub struct JournalClient {
+/// The name of the metrics counter for sent payloads.
+#[cfg(feature = "metrics")]
+const SENT_TOTAL: &str = "journal_logger_sent_total";
+
+/// The name of the metrics counter for dropped payloads.
+#[cfg(feature = "metrics")]
+const DROPPED_TOTAL: &str = "journal_logger_dropped_total";
+
+/// The type of socket to send to the journal through.
+///
+/// See [`crate::JournalLog::with_socket_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SocketType {
+    /// A `SOCK_DGRAM` socket, which journald listens on.
+    #[default]
+    Datagram,
+    /// A `SOCK_SEQPACKET` socket connected to the journal socket.
+    SeqPacket,
+}
+
+/// An open socket to journald.
+struct Connection {
     socket: UnixDatagram,
+    /// Payloads larger than this go through a memfd right away.
+    large_payload_threshold: usize,
+    /// The file this socket is bound to, which we remove when closing the socket.
+    bound_file: Option<PathBuf>,
+}
+
+impl Connection {
+    fn new(socket: UnixDatagram) -> Self {
+        Self {
+            large_payload_threshold: large_payload_threshold(&socket),
+            socket,
+            bound_file: None,
+        }
+    }
+
+    /// A connection through `socket` bound to `address`.
+    fn bound(socket: UnixDatagram, address: &Path) -> Self {
+        let mut connection = Self::new(socket);
+        connection.bound_file =
+            Some(address.to_path_buf()).filter(|path| !socket::is_abstract(path));
+        connection
+    }
+}
+
+impl Drop for Connection {
+    /// Remove the file the socket is bound to, so that it can be bound again.
+    fn drop(&mut self) {
+        if let Some(path) = &self.bound_file {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+pub struct JournalClient {
+    /// The socket, opened on first use unless opened explicitly.
+    connection: OnceLock<Connection>,
+    path: PathBuf,
+    socket_type: SocketType,
+    /// The address to bind datagram sockets to, if any.
+    bind_address: Option<PathBuf>,
+    /// How to retry transient send failures.
+    retry: RetryPolicy,
+    /// The send timeout for sockets, if any.
+    send_timeout: Option<Duration>,
+    /// Whether to enable `SO_PASSCRED` on sockets.
+    passcred: bool,
+    /// The number of payloads successfully sent.
+    sent: AtomicU64,
+    /// The number of payloads dropped.
+    dropped: AtomicU64,
+}
+
+/// Compute the large payload threshold for `socket`.
+///
+/// Use 75% of the send buffer size, to leave some headroom for the kernel's
+/// own accounting.  If we can't get the send buffer size, we just rely on
+/// `EMSGSIZE`.
+fn large_payload_threshold(socket: &UnixDatagram) -> usize {
+    socket::send_buffer_size(socket)
+        .map(|size| size / 4 * 3)
+        .unwrap_or(usize::MAX)
 }
 
 impl JournalClient {
     pub fn new() -> std::io::Result<Self> {
-        let client = Self {
-            socket: UnixDatagram::unbound()?,
-        };
+        let client = Self::unchecked();
         // Check that we can talk to journald, by sending empty payload which journald discards.
         // However if the socket didn't exist or if none listened we'd get an error here.
-        client.send_payload(&[])?;
+        // We don't go through send_payload, to not count this as a sent payload.
+        socket::send_to(client.socket()?, &[], &client.path)?;
         Ok(client)
     }
 
+    /// Create a client which opens its socket on first use.
+    pub fn unchecked() -> Self {
+        Self {
+            connection: OnceLock::new(),
+            path: PathBuf::from(JOURNALD_PATH),
+            socket_type: SocketType::Datagram,
+            bind_address: None,
+            retry: RetryPolicy::default(),
+            send_timeout: None,
+            passcred: true,
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a new client sending to the same socket path, with fresh counters.
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        let connection = match self.connection.get() {
+            // The clone shares the bound socket, but only the original removes its file
+            Some(connection) => OnceLock::from(Connection {
+                socket: connection.socket.try_clone()?,
+                large_payload_threshold: connection.large_payload_threshold,
+                bound_file: None,
+            }),
+            None => OnceLock::new(),
+        };
+        Ok(Self {
+            connection,
+            path: self.path.clone(),
+            socket_type: self.socket_type,
+            bind_address: self.bind_address.clone(),
+            retry: self.retry,
+            send_timeout: self.send_timeout,
+            passcred: self.passcred,
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the connection, and open a socket of the current type if there's none yet.
+    fn connection(&self) -> std::io::Result<&Connection> {
+        if let Some(connection) = self.connection.get() {
+            return Ok(connection);
+        }
+        let connection = match self.socket_type {
+            SocketType::SeqPacket => self.connect_seqpacket().map(Connection::new),
+            SocketType::Datagram => self.new_datagram_connection(),
+        };
+        let connection = match connection {
+            Ok(connection) => connection,
+            // Another thread may have been faster and bound the address already
+            Err(error) => return self.connection.get().ok_or(error),
+        };
+        // If another thread was faster, we just discard our socket
+        let _ = self.connection.set(connection);
+        Ok(self.connection.get().unwrap())
+    }
+
+    pub fn socket(&self) -> std::io::Result<&UnixDatagram> {
+        self.connection().map(|connection| &connection.socket)
+    }
+
+    /// Get the socket if it's open, without opening it.
+    pub fn open_socket(&self) -> Option<&UnixDatagram> {
+        self.connection.get().map(|connection| &connection.socket)
+    }
+
+    /// Close the socket; the next send opens a new one.
+    ///
+    /// Remove the file the socket is bound to, if any.
+    pub fn close(&mut self) {
+        self.connection = OnceLock::new();
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+        if self.socket_type == SocketType::SeqPacket {
+            // Reconnect to the new path
+            self.set_socket_type(SocketType::SeqPacket);
+        }
+    }
+
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    /// Send through a socket of the given `socket_type`.
+    ///
+    /// For [`SocketType::SeqPacket`] connect a new seqpacket socket to the
+    /// current path, and fall back to a datagram socket if that fails, e.g.
+    /// because the journal listens on a datagram socket.
+    pub fn set_socket_type(&mut self, socket_type: SocketType) {
+        if socket_type == SocketType::SeqPacket {
+            if let Ok(socket) = self.connect_seqpacket() {
+                self.connection = OnceLock::from(Connection::new(socket));
+                self.socket_type = SocketType::SeqPacket;
+                return;
+            }
+        }
+        if self.socket_type != SocketType::Datagram {
+            // Open the datagram socket on first use, to fail sends if binding fails
+            self.connection = OnceLock::new();
+            self.socket_type = SocketType::Datagram;
+        }
+    }
+
+    /// Create a new datagram connection, bound to the bind address if any.
+    ///
+    /// Fail if binding fails, e.g. because another socket already uses the
+    /// bind address.
+    fn new_datagram_connection(&self) -> std::io::Result<Connection> {
+        match &self.bind_address {
+            Some(address) => self.bind(address),
+            None => {
+                let socket = UnixDatagram::unbound()?;
+                self.configure(&socket)?;
+                Ok(Connection::new(socket))
+            }
+        }
+    }
+
+    /// Create a new datagram connection bound to `address`.
+    ///
+    /// Remove the socket file again if configuring the socket fails.
+    fn bind(&self, address: &Path) -> std::io::Result<Connection> {
+        let connection = Connection::bound(socket::bind_datagram(address)?, address);
+        self.configure(&connection.socket)?;
+        Ok(connection)
+    }
+
+    /// Connect a new seqpacket socket to the current path.
+    fn connect_seqpacket(&self) -> std::io::Result<UnixDatagram> {
+        let socket = socket::connect_seqpacket(&self.path)?;
+        self.configure(&socket)?;
+        Ok(socket)
+    }
+
+    /// Apply the send timeout and `SO_PASSCRED` setting to a new `socket`.
+    fn configure(&self, socket: &UnixDatagram) -> std::io::Result<()> {
+        socket.set_write_timeout(self.send_timeout)?;
+        socket::set_passcred(socket, self.passcred)
+    }
+
+    /// Bind datagram sockets to `address`.
+    ///
+    /// If the client currently uses a datagram socket replace it with a socket
+    /// bound to `address`.  Keep the current socket and the current bind
+    /// address if binding fails.
+    pub fn set_bind_address(&mut self, address: PathBuf) -> std::io::Result<()> {
+        if self.socket_type == SocketType::Datagram {
+            self.connection = OnceLock::from(self.bind(&address)?);
+        }
+        self.bind_address = Some(address);
+        Ok(())
+    }
+
+    /// Time out sends after `timeout`, or never time out if `None`.
+    ///
+    /// Apply `timeout` to the current socket, if any, and to all later sockets.
+    pub fn set_send_timeout(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.send_timeout = timeout;
+        match self.open_socket() {
+            Some(socket) => socket.set_write_timeout(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// Enable or disable `SO_PASSCRED`.
+    ///
+    /// Apply the setting to the current socket, if any, and to all later sockets.
+    pub fn set_passcred(&mut self, enabled: bool) -> std::io::Result<()> {
+        self.passcred = enabled;
+        match self.open_socket() {
+            Some(socket) => socket::set_passcred(socket, enabled),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_retry(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    pub fn large_payload_threshold(&self) -> std::io::Result<usize> {
+        self.connection()
+            .map(|connection| connection.large_payload_threshold)
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Count a sent payload.
+    ///
+    /// [`Self::send_payload`] counts its payloads itself; this is for payloads
+    /// sent by other means, e.g. a custom transport.
+    pub fn count_sent(&self) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!(SENT_TOTAL).increment(1);
+    }
+
+    /// Count a dropped payload.
+    ///
+    /// Like [`Self::count_sent`] this is for payloads which didn't go through
+    /// [`Self::send_payload`], e.g. because a queue was full.
+    pub fn count_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!(DROPPED_TOTAL).increment(1);
+    }
+
     /// Send `payload` to journald.
     ///
     /// Directly send it as datagram, and fall back to [`Self::send_large_payload`]
-    /// if that fails with `EMSGSIZE`.
+    /// if that fails with `EMSGSIZE`.  If `payload` exceeds the large payload
+    /// threshold derived from the socket's send buffer size, directly use
+    /// [`Self::send_large_payload`].
+    ///
+    /// Retry transient failures according to the retry policy, and count the
+    /// payload as sent or dropped, depending on the result.
     pub fn send_payload(&self, payload: &[u8]) -> std::io::Result<usize> {
-        self.socket
-            .send_to(payload, JOURNALD_PATH)
-            .or_else(|error| {
-                if Some(libc::EMSGSIZE) == error.raw_os_error() {
+        let result = self.connection().and_then(|connection| {
+            self.retry.run(|| {
+                if connection.large_payload_threshold < payload.len() {
                     self.send_large_payload(payload)
                 } else {
-                    Err(error)
+                    socket::send_to(&connection.socket, payload, &self.path).or_else(|error| {
+                        if Some(libc::EMSGSIZE) == error.raw_os_error() {
+                            self.send_large_payload(payload)
+                        } else {
+                            Err(error)
+                        }
+                    })
                 }
             })
+        });
+        match result {
+            Ok(_) => self.count_sent(),
+            Err(_) => self.count_dropped(),
+        };
+        result
     }
 
     pub fn send_large_payload(&self, payload: &[u8]) -> std::io::Result<usize> {
@@ -56,6 +377,6 @@ impl JournalClient {
         // Fully seal the memfd to signal journald that its backing data won't resize anymore
         // and so is safe to mmap.
         memfd::seal_fully(mem.as_fd())?;
-        socket::send_one_fd_to(&self.socket, mem.as_fd(), JOURNALD_PATH)
+        socket::send_one_fd_to(self.socket()?, mem.as_fd(), &self.path)
     }
 }
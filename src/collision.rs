@@ -0,0 +1,208 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Handle distinct keys which escape to the same field name.
+
+use log::kv::Value;
+
+/// What to do with distinct keys which escape to the same field name.
+///
+/// The logger escapes keys of key-values to valid journal field names (see
+/// [`crate::JournalLog`]), so distinct keys like `Foo` and `foo` may collide
+/// on the same field name `FOO`.  Repeated keys which are identical never
+/// collide; the logger always writes all their values.
+///
+/// See [`crate::JournalLog::with_collision_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CollisionPolicy {
+    /// Write the values of all colliding keys, as multiple values of the field.
+    #[default]
+    Keep,
+    /// Only write the values of the last colliding key of the record.
+    Overwrite,
+    /// Fail to send the record.
+    Error,
+    /// Append `_2`, `_3`, etc. to the field names of later colliding keys.
+    ///
+    /// Skip suffixes which would collide with the field name of another key;
+    /// with keys `Foo`, `foo` and `foo_2` the logger writes `foo` as `FOO_3`.
+    Suffix,
+}
+
+/// Key-values of a record with their escaped names, in the order of the record.
+pub type EscapedKeyValues<'kvs> = Vec<(Vec<u8>, Value<'kvs>)>;
+
+/// Find the first collision in `key_values` with original `keys`.
+///
+/// Return the indexes of the two colliding key-values.
+pub fn find_collision(keys: &[&str], key_values: &EscapedKeyValues) -> Option<(usize, usize)> {
+    key_values.iter().enumerate().find_map(|(j, (name, _))| {
+        key_values[..j]
+            .iter()
+            .position(|(other, _)| other == name)
+            .filter(|&i| keys[i] != keys[j])
+            .map(|i| (i, j))
+    })
+}
+
+/// Resolve collisions in `key_values` with original `keys` according to `policy`.
+///
+/// Do nothing for [`CollisionPolicy::Keep`] and [`CollisionPolicy::Error`].
+pub fn resolve(policy: CollisionPolicy, keys: &[&str], key_values: &mut EscapedKeyValues) {
+    match policy {
+        CollisionPolicy::Keep | CollisionPolicy::Error => {}
+        CollisionPolicy::Overwrite => {
+            let last_keys: Vec<&str> = key_values
+                .iter()
+                .map(|(name, _)| {
+                    let last = key_values.iter().rposition(|(other, _)| other == name);
+                    keys[last.unwrap()]
+                })
+                .collect();
+            let mut index = 0;
+            key_values.retain(|_| {
+                let keep = keys[index] == last_keys[index];
+                index += 1;
+                keep
+            });
+        }
+        CollisionPolicy::Suffix => {
+            let names: Vec<Vec<u8>> = key_values.iter().map(|(name, _)| name.clone()).collect();
+            // All field names in use, to not suffix a key onto the name of another key
+            let mut taken = names.clone();
+            // The field name of every distinct key, by its escaped name
+            let mut assigned: Vec<(&[u8], &str, Vec<u8>)> = Vec::new();
+            for (((name, _), original), key) in key_values.iter_mut().zip(&names).zip(keys) {
+                if let Some((_, _, suffixed)) = assigned
+                    .iter()
+                    .find(|(other, other_key, _)| *other == original.as_slice() && other_key == key)
+                {
+                    *name = suffixed.clone();
+                    continue;
+                }
+                if assigned
+                    .iter()
+                    .any(|(other, _, _)| *other == original.as_slice())
+                {
+                    let mut index = 2;
+                    *name = loop {
+                        let candidate = suffix_name(original, index);
+                        if !taken.contains(&candidate) {
+                            break candidate;
+                        }
+                        index += 1;
+                    };
+                    taken.push(name.clone());
+                }
+                assigned.push((original, key, name.clone()));
+            }
+        }
+    }
+}
+
+/// Append `_index` to `name`, within the maximum length of field names.
+fn suffix_name(name: &[u8], index: usize) -> Vec<u8> {
+    let suffix = format!("_{}", index);
+    let mut name = name[..name.len().min(64 - suffix.len())].to_vec();
+    name.extend_from_slice(suffix.as_bytes());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use log::kv::Value;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    fn escaped<'a>(keys: &[&str], values: &[&'a str]) -> EscapedKeyValues<'a> {
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| (key.to_ascii_uppercase().into_bytes(), Value::from(*value)))
+            .collect()
+    }
+
+    fn names_and_values(key_values: &EscapedKeyValues) -> Vec<(String, String)> {
+        key_values
+            .iter()
+            .map(|(name, value)| (String::from_utf8(name.clone()).unwrap(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn find_collision_ignores_identical_keys() {
+        let keys = ["foo", "foo", "bar"];
+        let key_values = escaped(&keys, &["1", "2", "3"]);
+        assert_eq!(find_collision(&keys, &key_values), None);
+    }
+
+    #[test]
+    fn find_collision_of_distinct_keys() {
+        let keys = ["Foo", "bar", "foo"];
+        let key_values = escaped(&keys, &["1", "2", "3"]);
+        assert_eq!(find_collision(&keys, &key_values), Some((0, 2)));
+    }
+
+    #[test]
+    fn resolve_overwrite() {
+        let keys = ["Foo", "bar", "foo", "foo"];
+        let mut key_values = escaped(&keys, &["1", "2", "3", "4"]);
+        resolve(CollisionPolicy::Overwrite, &keys, &mut key_values);
+        assert_eq!(
+            names_and_values(&key_values),
+            vec![
+                ("BAR".to_string(), "2".to_string()),
+                ("FOO".to_string(), "3".to_string()),
+                ("FOO".to_string(), "4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_suffix() {
+        let keys = ["Foo", "foo", "FOO", "foo"];
+        let mut key_values = escaped(&keys, &["1", "2", "3", "4"]);
+        resolve(CollisionPolicy::Suffix, &keys, &mut key_values);
+        assert_eq!(
+            names_and_values(&key_values),
+            vec![
+                ("FOO".to_string(), "1".to_string()),
+                ("FOO_2".to_string(), "2".to_string()),
+                ("FOO_3".to_string(), "3".to_string()),
+                ("FOO_2".to_string(), "4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_suffix_skips_taken_names() {
+        let keys = ["Foo", "foo", "foo_2", "foo"];
+        let mut key_values = escaped(&keys, &["1", "2", "3", "4"]);
+        resolve(CollisionPolicy::Suffix, &keys, &mut key_values);
+        assert_eq!(
+            names_and_values(&key_values),
+            vec![
+                ("FOO".to_string(), "1".to_string()),
+                ("FOO_3".to_string(), "2".to_string()),
+                ("FOO_2".to_string(), "3".to_string()),
+                ("FOO_3".to_string(), "4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_suffix_keeps_maximum_length() {
+        let long = "a".repeat(64);
+        let other = "A".repeat(64);
+        let keys = [long.as_str(), other.as_str()];
+        let mut key_values = escaped(&keys, &["1", "2"]);
+        resolve(CollisionPolicy::Suffix, &keys, &mut key_values);
+        assert_eq!(key_values[1].0.len(), 64);
+        assert!(key_values[1].0.ends_with(b"_2"));
+    }
+}
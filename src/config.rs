@@ -0,0 +1,129 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A snapshot of the configuration of a logger.
+
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+
+use log::LevelFilter;
+
+use crate::fields::{escape_field_name, put_field_bytes, FieldName};
+use crate::{SocketType, StandardFields};
+
+/// The effective configuration of a [`crate::JournalLog`].
+///
+/// See [`crate::JournalLog::config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JournalConfig {
+    /// The syslog identifier.
+    ///
+    /// Records may still use different identifiers, see
+    /// [`crate::JournalLog::with_identifier_map`].
+    pub syslog_identifier: String,
+    /// The escaped names and the values of extra fields, in order.
+    pub extra_fields: Vec<(String, Vec<u8>)>,
+    /// The maximum level of records to send.
+    pub max_level: LevelFilter,
+    /// Which standard fields the logger writes.
+    pub standard_fields: StandardFields,
+    /// The name of the field for the target of a record.
+    pub target_field_name: String,
+    /// The path of the journal socket the logger sends to.
+    pub socket_path: PathBuf,
+    /// The type of socket the logger sends through.
+    pub socket_type: SocketType,
+}
+
+/// The part of the configuration of a logger which it can replace atomically.
+///
+/// See [`crate::JournalLog::reconfigure`].
+#[derive(Clone)]
+pub(crate) struct Settings {
+    /// Preformatted extra fields to be appended to every log message.
+    pub extra_fields: Vec<u8>,
+    /// Escaped names and values of extra fields, for merging.
+    pub extra_field_values: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Names of extra fields as given, for validation.
+    pub extra_field_names: Vec<String>,
+    /// The syslog identifier.
+    pub syslog_identifier: String,
+    /// The maximum level of records to send.
+    pub max_level: LevelFilter,
+    /// Which standard fields to write.
+    pub standard_fields: StandardFields,
+    /// The name of the field for the target of a record.
+    pub target_field_name: String,
+}
+
+/// Settings shared between a logger and its panic hook.
+pub(crate) type SharedSettings = Arc<RwLock<Arc<Settings>>>;
+
+/// Write access to shared settings.
+///
+/// Clone the settings on the first write if a record still holds them.
+pub(crate) struct SettingsMut<'a>(pub RwLockWriteGuard<'a, Arc<Settings>>);
+
+impl Deref for SettingsMut<'_> {
+    type Target = Settings;
+
+    fn deref(&self) -> &Settings {
+        &self.0
+    }
+}
+
+impl DerefMut for SettingsMut<'_> {
+    fn deref_mut(&mut self) -> &mut Settings {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            extra_fields: Vec::new(),
+            extra_field_values: Vec::new(),
+            extra_field_names: Vec::new(),
+            syslog_identifier: String::new(),
+            max_level: LevelFilter::Trace,
+            standard_fields: StandardFields::default(),
+            target_field_name: "TARGET".to_string(),
+        }
+    }
+
+    /// Create settings from the corresponding fields of `config`.
+    pub fn from_config(config: JournalConfig) -> Self {
+        let mut settings = Self {
+            syslog_identifier: config.syslog_identifier,
+            max_level: config.max_level,
+            standard_fields: config.standard_fields,
+            target_field_name: config.target_field_name,
+            ..Self::new()
+        };
+        for (name, value) in &config.extra_fields {
+            // Escaping an escaped name doesn't change it
+            settings.add_extra_field(name, value);
+        }
+        settings
+    }
+
+    pub fn add_extra_field(&mut self, name: &str, value: &[u8]) {
+        put_field_bytes(&mut self.extra_fields, FieldName::WriteEscaped(name), value);
+        self.extra_field_values
+            .push((escape_field_name(name), value.to_vec()));
+        self.extra_field_names.push(name.to_string());
+    }
+
+    pub fn clear_extra_fields(&mut self) {
+        self.extra_fields.clear();
+        self.extra_field_values.clear();
+        self.extra_field_names.clear();
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Typed context for individual records.
+
+use log::kv::Value;
+
+use crate::Priority;
+
+/// Fields and overrides for a single record.
+///
+/// Build a context with [`Self::field`] and [`Self::priority`], and log
+/// records with it through [`crate::JournalLog::log_with_context`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JournalContext {
+    fields: Vec<(String, String)>,
+    priority: Option<Priority>,
+}
+
+impl JournalContext {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field `name` with `value`.
+    ///
+    /// The logger writes context fields like key-values of the record, i.e.
+    /// it escapes `name`, and context fields override default fields of the
+    /// logger (see [`crate::JournalLog::with_default_fields`]).
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Override the priority of the record.
+    ///
+    /// The logger adds the severity name of `priority` as `severity` field to
+    /// the record, which takes precedence over the level of the record (see
+    /// [`crate::JournalLog`]).
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Get the key-values of this context.
+    pub(crate) fn key_values(&self) -> Vec<(&str, Value<'_>)> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), Value::from(value.as_str())));
+        let severity = self
+            .priority
+            .map(|priority| ("severity", Value::from(priority.severity_name())));
+        fields.chain(severity).collect()
+    }
+}
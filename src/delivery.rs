@@ -0,0 +1,24 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Where records went.
+
+/// Where a record went.
+///
+/// See [`crate::JournalLog::journal_send`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Delivery {
+    /// The record went to the journal.
+    Journal,
+    /// The journal was unavailable, so the record went to syslog instead.
+    ///
+    /// See [`crate::JournalLog::with_syslog_fallback`].
+    Syslog,
+    /// The logger discarded the record, e.g. because of sampling.
+    Discarded,
+}
@@ -0,0 +1,77 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Diagnostics about the internal state of a logger.
+
+use std::path::PathBuf;
+
+use crate::client::{JournalClient, SocketType};
+use crate::socket;
+
+/// Diagnostic information about a [`crate::JournalLog`].
+///
+/// See [`crate::JournalLog::diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JournalDiagnostics {
+    /// The path of the journal socket the logger sends to.
+    pub socket_path: PathBuf,
+    /// The type of socket the logger sends through.
+    ///
+    /// This is [`SocketType::Datagram`] if the logger fell back to a datagram
+    /// socket (see [`crate::JournalLog::with_socket_type`]).
+    pub socket_type: SocketType,
+    /// Whether the logger currently has a socket open.
+    ///
+    /// The logger opens its socket on first use, and closes it when disabled
+    /// (see [`crate::JournalLog::with_max_level`]).
+    pub connected: bool,
+    /// The send buffer size of the socket (`SO_SNDBUF`), if available.
+    pub send_buffer_size: Option<usize>,
+    /// Whether the socket is in non-blocking mode, if available.
+    pub nonblocking: Option<bool>,
+    /// Whether the socket has `SO_PASSCRED` enabled, if available.
+    ///
+    /// See [`crate::JournalLog::with_passcred`].
+    pub passcred: Option<bool>,
+    /// The size in bytes above which the logger sends entries through a memfd.
+    ///
+    /// The logger derives this threshold from the send buffer size when
+    /// connecting to the journal, and sends smaller entries directly as
+    /// datagrams.  Without an open socket the threshold is `usize::MAX`.
+    pub large_payload_threshold: usize,
+    /// The number of records successfully sent to the journal.
+    pub sent: u64,
+    /// The number of records dropped, because sending failed.
+    pub dropped: u64,
+}
+
+impl JournalDiagnostics {
+    pub(crate) fn from_client(client: &JournalClient) -> Self {
+        Self {
+            socket_path: client.path().to_path_buf(),
+            socket_type: client.socket_type(),
+            connected: client.open_socket().is_some(),
+            send_buffer_size: client
+                .open_socket()
+                .and_then(|socket| socket::send_buffer_size(socket).ok()),
+            nonblocking: client
+                .open_socket()
+                .and_then(|socket| socket::is_nonblocking(socket).ok()),
+            passcred: client
+                .open_socket()
+                .and_then(|socket| socket::passcred(socket).ok()),
+            large_payload_threshold: client
+                .open_socket()
+                .and_then(|_| client.large_payload_threshold().ok())
+                .unwrap_or(usize::MAX),
+            sent: client.sent(),
+            dropped: client.dropped(),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Handle key-values with empty values.
+
+use log::kv::Value;
+
+/// What to do with key-values whose value is empty.
+///
+/// See [`crate::JournalLog::with_empty_field_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmptyFieldPolicy {
+    /// Write the field with an empty value, e.g. `NOTE=`.
+    #[default]
+    Keep,
+    /// Omit the field.
+    Drop,
+}
+
+/// Whether `value` is empty when formatted.
+pub fn is_empty_value(value: &Value) -> bool {
+    match value.to_borrowed_str() {
+        Some(value) => value.is_empty(),
+        None => value.to_string().is_empty(),
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Handle records with empty messages.
+
+use log::Record;
+
+/// What to do with records whose message is empty.
+///
+/// See [`crate::JournalLog::with_empty_message_policy`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum EmptyMessagePolicy {
+    /// Send the record with an empty `MESSAGE`.
+    #[default]
+    Send,
+    /// Discard the record.
+    Skip,
+    /// Send the record with the given text as `MESSAGE`.
+    Placeholder(String),
+}
+
+/// Whether the message of `record` is empty.
+pub fn is_empty_message(record: &Record) -> bool {
+    match record.args().as_str() {
+        Some(message) => message.is_empty(),
+        None => record.args().to_string().is_empty(),
+    }
+}
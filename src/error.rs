@@ -0,0 +1,125 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The error type of this crate.
+
+use std::fmt::{Display, Formatter};
+
+/// An error of a journal logger.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O operation failed, e.g. sending a record to the journal.
+    Io(std::io::Error),
+    /// A name is not a valid journal field name.
+    InvalidFieldName(String),
+    /// A name is not a valid journal namespace.
+    InvalidNamespace(String),
+    /// Two distinct keys of a record escape to the same field name.
+    ///
+    /// See [`crate::CollisionPolicy::Error`].
+    FieldCollision {
+        /// The first of the colliding keys.
+        first: String,
+        /// The second of the colliding keys.
+        second: String,
+        /// The field name both keys escape to.
+        field: String,
+    },
+    /// The logger could not connect to the journal.
+    SocketUnavailable(std::io::Error),
+}
+
+impl Error {
+    /// The underlying I/O error, if any.
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            Self::Io(error) | Self::SocketUnavailable(error) => Some(error),
+            Self::InvalidFieldName(_) | Self::InvalidNamespace(_) | Self::FieldCollision { .. } => {
+                None
+            }
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            Self::InvalidFieldName(name) => write!(f, "{} is not a valid journal field name", name),
+            Self::InvalidNamespace(name) => write!(f, "{} is not a valid journal namespace", name),
+            Self::FieldCollision {
+                first,
+                second,
+                field,
+            } => write!(
+                f,
+                "keys {} and {} both escape to field {}",
+                first, second, field
+            ),
+            Self::SocketUnavailable(error) => write!(f, "failed to connect to journal: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) | Self::SocketUnavailable(error) => Some(error),
+            Self::InvalidFieldName(_) | Self::InvalidNamespace(_) | Self::FieldCollision { .. } => {
+                None
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Convert errors to I/O errors, e.g. for [`std::io::Write`] implementations.
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) | Error::SocketUnavailable(error) => error,
+            other => std::io::Error::new(std::io::ErrorKind::InvalidInput, other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use similar_asserts::assert_eq;
+
+    use super::Error;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Error::InvalidFieldName("foo".to_string()).to_string(),
+            "foo is not a valid journal field name"
+        );
+        assert_eq!(
+            Error::SocketUnavailable(ErrorKind::NotFound.into()).to_string(),
+            "failed to connect to journal: entity not found"
+        );
+    }
+
+    #[test]
+    fn into_io_error() {
+        let error = std::io::Error::from(Error::Io(ErrorKind::WouldBlock.into()));
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        let error = std::io::Error::from(Error::InvalidNamespace("a/b".to_string()));
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "a/b is not a valid journal namespace");
+    }
+}
@@ -8,14 +8,18 @@
 
 //! Write well-formated journal fields to buffers.
 
+use std::borrow::Cow;
 use std::fmt::Arguments;
 use std::io::Write;
 
 use log::kv::Value;
 
+#[derive(Clone, Copy)]
 pub enum FieldName<'a> {
     WellFormed(&'a str),
     WriteEscaped(&'a str),
+    /// A name already escaped with [`escape_field_name`].
+    Escaped(&'a [u8]),
 }
 
 /// Whether `c` is a valid character in the key of a journal field.
@@ -26,6 +30,16 @@ fn is_valid_key_char(c: char) -> bool {
     matches!(c, 'A'..='Z' | '0'..='9' | '_')
 }
 
+/// Whether `name` is a valid name of a journal field which clients may set.
+///
+/// Valid names consist of 1 to 64 ASCII uppercase letters, numbers and
+/// underscores, and start with a letter.
+pub fn is_valid_field_name(name: &str) -> bool {
+    name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(is_valid_key_char)
+}
+
 /// Escape a `key` for use in a systemd journal field.
 ///
 /// See [`crate::JournalLog`] for these rules.
@@ -41,15 +55,66 @@ pub fn escape_journal_key(key: &str) -> Vec<u8> {
     payload
 }
 
+/// Escape a field `name` for use in a systemd journal field.
+///
+/// Like [`escape_journal_key`] but use `EMPTY` for an empty `name`.
+pub fn escape_field_name(name: &str) -> Vec<u8> {
+    if name.is_empty() {
+        b"EMPTY".to_vec()
+    } else {
+        escape_journal_key(name)
+    }
+}
+
+/// Append `suffix` to the escaped field `name`.
+///
+/// Truncate `name` to make room for `suffix` within the maximum length of
+/// field names.
+pub fn suffix_field_name(name: &[u8], suffix: &[u8]) -> Vec<u8> {
+    let mut suffixed = name[..name.len().min(64 - suffix.len())].to_vec();
+    suffixed.extend_from_slice(suffix);
+    suffixed
+}
+
+/// Truncate `value` to at most `max_len` bytes.
+///
+/// Back off to the nearest preceding char boundary, to never split a UTF-8
+/// code point.
+pub fn truncate_at_char_boundary(value: &str, max_len: usize) -> &str {
+    if value.len() <= max_len {
+        value
+    } else {
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        &value[..end]
+    }
+}
+
+/// Remove a single trailing newline from `value`.
+///
+/// Also remove a carriage return preceding the newline.
+pub fn trim_trailing_newline(value: &str) -> &str {
+    match value.strip_suffix('\n') {
+        Some(value) => value.strip_suffix('\r').unwrap_or(value),
+        None => value,
+    }
+}
+
 fn put_field_name(buffer: &mut Vec<u8>, name: FieldName<'_>) {
     match name {
         FieldName::WellFormed(name) => buffer.extend_from_slice(name.as_bytes()),
-        FieldName::WriteEscaped(name) if name.is_empty() => buffer.extend_from_slice(b"EMPTY"),
+        FieldName::WriteEscaped("") => buffer.extend_from_slice(b"EMPTY"),
         // FIXME: We should try to find a way to do this with less allocations.
         FieldName::WriteEscaped(name) => buffer.extend_from_slice(&escape_journal_key(name)),
+        FieldName::Escaped(name) => buffer.extend_from_slice(name),
     }
 }
 
+/// The maximum size of a single field journald accepts, i.e. `DATA_SIZE_MAX`.
+pub const MAX_FIELD_SIZE: usize = 768 * 1024 * 1024;
+
 pub trait PutAsFieldValue {
     fn put_field_value(self, buffer: &mut Vec<u8>);
 }
@@ -94,6 +159,45 @@ pub fn put_field_length_encoded<V: PutAsFieldValue>(
     buffer.push(b'\n');
 }
 
+/// Formats into a byte buffer.
+///
+/// Unlike [`std::io::Write`] for `Vec<u8>` this returns errors of formatting
+/// trait implementations instead of panicking.
+struct FormatBytes<'a>(&'a mut Vec<u8>);
+
+impl std::fmt::Write for FormatBytes<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Put a length encoded field with `value`, unless formatting `value` fails.
+///
+/// If formatting `value` fails leave `buffer` unchanged, and return the error.
+pub fn try_put_field_length_encoded(
+    buffer: &mut Vec<u8>,
+    name: FieldName<'_>,
+    value: &Value,
+) -> std::fmt::Result {
+    let start = buffer.len();
+    put_field_name(buffer, name);
+    buffer.push(b'\n');
+    // Reserve the length tag
+    buffer.extend_from_slice(&[0; 8]);
+    let value_start = buffer.len();
+    if let Err(error) = std::fmt::write(&mut FormatBytes(buffer), format_args!("{}", value)) {
+        buffer.truncate(start);
+        return Err(error);
+    }
+    let value_end = buffer.len();
+    // Fill the length tag
+    let length_bytes = ((value_end - value_start) as u64).to_le_bytes();
+    buffer[value_start - 8..value_start].copy_from_slice(&length_bytes);
+    buffer.push(b'\n');
+    Ok(())
+}
+
 pub fn put_field_bytes(buffer: &mut Vec<u8>, name: FieldName<'_>, value: &[u8]) {
     if value.contains(&b'\n') {
         // Write as length encoded field
@@ -106,12 +210,54 @@ pub fn put_field_bytes(buffer: &mut Vec<u8>, name: FieldName<'_>, value: &[u8])
     }
 }
 
+/// Put standard `fields` with well-formed names into `buffer`.
+///
+/// Return the length of `MESSAGE` in bytes, if written.
+pub fn put_standard_fields<'a>(
+    buffer: &mut Vec<u8>,
+    fields: impl IntoIterator<Item = (&'a str, Cow<'a, [u8]>)>,
+) -> Option<usize> {
+    let mut message_len = None;
+    for (name, value) in fields {
+        if name == "MESSAGE" {
+            message_len = Some(value.len());
+            // Always length-encode the message, because it's the most likely field to
+            // contain newlines.
+            put_field_length_encoded(buffer, FieldName::WellFormed(name), value.as_ref());
+        } else if name == "SYSLOG_IDENTIFIER" && value.iter().any(u8::is_ascii_control) {
+            // Identifiers may come from the environment and contain arbitrary bytes, so
+            // length-encode them if they contain any control character, not just newlines.
+            put_field_length_encoded(buffer, FieldName::WellFormed(name), value.as_ref());
+        } else {
+            put_field_bytes(buffer, FieldName::WellFormed(name), &value);
+        }
+    }
+    message_len
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use similar_asserts::assert_eq;
     use FieldName::*;
 
+    #[test]
+    fn is_valid_field_name() {
+        for name in ["LOGGER", "A", "FOO_123", &"A".repeat(64)] {
+            assert!(super::is_valid_field_name(name), "{}", name);
+        }
+        for name in [
+            "",
+            "logger",
+            "_LOGGER",
+            "1LOGGER",
+            "LOG-GER",
+            &"A".repeat(65),
+        ] {
+            assert!(!super::is_valid_field_name(name), "{}", name);
+        }
+    }
+
     #[test]
     fn escape_journal_key() {
         for case in &["FOO", "FOO_123"] {
@@ -135,6 +281,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn truncate_at_char_boundary() {
+        assert_eq!(super::truncate_at_char_boundary("foo", 5), "foo");
+        assert_eq!(super::truncate_at_char_boundary("foobar", 3), "foo");
+        // Each ö takes two bytes, so three bytes would split the second ö
+        assert_eq!(super::truncate_at_char_boundary("ööö", 3), "ö");
+        assert_eq!(super::truncate_at_char_boundary("ööö", 4), "öö");
+        // A four byte emoji
+        assert_eq!(super::truncate_at_char_boundary("a🦀", 4), "a");
+        assert_eq!(super::truncate_at_char_boundary("🦀", 0), "");
+    }
+
+    #[test]
+    fn trim_trailing_newline() {
+        assert_eq!(super::trim_trailing_newline("foo"), "foo");
+        assert_eq!(super::trim_trailing_newline("foo\n"), "foo");
+        assert_eq!(super::trim_trailing_newline("foo\r\n"), "foo");
+        assert_eq!(super::trim_trailing_newline("foo\n\n"), "foo\n");
+        assert_eq!(super::trim_trailing_newline("foo\r"), "foo\r");
+    }
+
     #[test]
     fn put_field_length_encoded() {
         let mut buffer = Vec::new();
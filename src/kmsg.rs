@@ -0,0 +1,71 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Write records to the kernel log.
+
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+
+use log::Record;
+
+use crate::syslog::FACILITY_USER;
+
+/// The path of the kernel log device.
+pub const KMSG_PATH: &str = "/dev/kmsg";
+
+/// Format `record` as a line for the kernel log.
+///
+/// `priority` is the journal priority of `record`; like syslog messages the
+/// line starts with the priority in the user facility, followed by the
+/// identifier, the process id and the message.
+pub fn format_record(priority: u8, identifier: &str, pid: u32, record: &Record) -> Vec<u8> {
+    let mut line = Vec::with_capacity(256);
+    writeln!(
+        line,
+        "<{}>{}[{}]: {}",
+        FACILITY_USER * 8 + priority,
+        identifier,
+        pid,
+        record.args()
+    )
+    .unwrap();
+    line
+}
+
+/// Write `line` to the kernel log.
+///
+/// Open the kernel log for every line; writing requires privileges.
+pub fn write(line: &[u8]) -> std::io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(KMSG_PATH)?
+        .write_all(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Record};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn format_record() {
+        let line = super::format_record(
+            3,
+            "initrd-tool",
+            42,
+            &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("mount failed"))
+                .build(),
+        );
+        assert_eq!(
+            String::from_utf8(line).unwrap(),
+            "<11>initrd-tool[42]: mount failed\n"
+        );
+    }
+}
@@ -57,24 +57,97 @@
 //!
 //! You can display these extra fields with `journalctl --output=verbose` and extract them with any of the structured
 //! output formats of `journalctl`, e.g. `journalctl --output=json`.
+//!
+//! ## Structured fields
+//!
+//! The logger writes the key-values of log records as journal fields (see
+//! [`JournalLog`]).  This crate always enables the key-value support of the
+//! [`log`] crate, so key-values are never silently dropped, regardless of the
+//! features other crates in the dependency graph enable for [`log`].
+//!
+//! The logger writes error values of key-values, e.g. captured with
+//! `log::info!(err:err = error; "...")`, in two fields: the key with the
+//! `Display` representation of the error, and the key with a `_DEBUG` suffix
+//! with its `Debug` representation.
 
 #![deny(warnings, missing_docs, clippy::all)]
 
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::io::prelude::*;
+use std::net::{IpAddr, SocketAddr};
 use std::os::fd::AsFd;
 use std::os::linux::fs::MetadataExt;
+use std::os::unix::ffi::OsStrExt;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
 
 use client::JournalClient;
-use log::kv::{Error, Key, Value, Visitor};
-use log::{Level, Log, Metadata, Record, SetLoggerError};
+use log::kv::{Key, Value, Visitor};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 
+#[cfg(feature = "async")]
+mod background;
 mod client;
+mod collision;
+mod config;
+mod context;
+mod delivery;
+mod diagnostics;
+mod empty_field;
+mod empty_message;
+mod error;
 mod fields;
+mod kmsg;
+mod line_writer;
 mod memfd;
+mod multi_log;
+mod namespace;
+#[cfg(feature = "otel")]
+mod otel;
+mod panic_hook;
+mod priority;
+mod redact;
+mod retry;
+mod ring;
+mod sampling;
+mod scoped_fields;
+#[cfg(feature = "serde")]
+mod serialize;
 mod socket;
+mod standard_fields;
+mod stderr;
+mod sync;
+mod syslog;
+mod timestamp;
+mod transport;
+mod unicode_field;
 
 use fields::*;
 
+pub use client::SocketType;
+pub use collision::CollisionPolicy;
+pub use config::JournalConfig;
+pub use context::JournalContext;
+pub use delivery::Delivery;
+pub use diagnostics::JournalDiagnostics;
+pub use empty_field::EmptyFieldPolicy;
+pub use empty_message::EmptyMessagePolicy;
+pub use error::Error;
+pub use multi_log::MultiLog;
+pub use priority::Priority;
+pub use ring::EntrySnapshot;
+pub use standard_fields::StandardFields;
+pub use transport::JournalTransport;
+pub use unicode_field::UnicodeFieldPolicy;
+
+#[cfg(feature = "async")]
+pub use background::AsyncJournalLog;
+
 /// Whether the current process is directly connected to the systemd journal.
 ///
 /// Return `true` if the device and inode numbers of the [`std::io::stderr`]
@@ -93,6 +166,67 @@ pub fn connected_to_journal() -> bool {
         .unwrap_or(false)
 }
 
+/// Create a syslog identifier from the kernel's name of the current process.
+///
+/// Read the name from `/proc/self/comm`, which the kernel truncates to 15
+/// bytes.  Return `None` if reading the name failed.
+pub fn comm_identifier() -> Option<String> {
+    let comm = std::fs::read_to_string("/proc/self/comm").ok()?;
+    Some(comm.trim_end_matches('\n').to_string()).filter(|comm| !comm.is_empty())
+}
+
+/// Format `when` as RFC 3339 timestamp for a journal field.
+///
+/// Return `when` in UTC with microsecond precision, e.g.
+/// `2023-10-01T12:00:00.123456Z`, which is easier to read and to parse than
+/// the debug representation of timestamps.  Use this function to add
+/// timestamps as key-values to records, e.g. with
+/// `log::info!(created = field_datetime(created); "...")`.
+///
+/// Types of date time libraries usually convert to [`SystemTime`], e.g.
+/// `SystemTime::from(chrono_date_time)`.
+pub fn field_datetime(when: SystemTime) -> String {
+    timestamp::rfc3339(when)
+}
+
+/// Format `uuid` as canonical UUID string for a journal field.
+///
+/// Return the hyphenated lowercase form of `uuid`, e.g.
+/// `67e55044-10b1-426f-9247-bb680e5fe0c8`, where `uuid` holds the bytes of
+/// the UUID in big-endian order.  Use this function to add UUIDs as
+/// key-values to records, e.g. with
+/// `log::info!(request = field_uuid(request_id.as_u128()); "...")` for a
+/// `Uuid` of the `uuid` crate.
+pub fn field_uuid(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Format `ip` as canonical address string for a journal field.
+///
+/// Return IPv4 addresses in dotted decimal notation, e.g. `192.0.2.1`, and
+/// IPv6 addresses in the compressed lowercase notation of RFC 5952, e.g.
+/// `2001:db8::1`.  Use this function to add addresses as key-values to
+/// records, e.g. with `log::info!(peer = field_ip(peer); "...")`.
+pub fn field_ip(ip: IpAddr) -> String {
+    ip.to_string()
+}
+
+/// Format `address` as canonical socket address string for a journal field.
+///
+/// Like [`field_ip`], but append the port, and enclose IPv6 addresses in
+/// brackets, e.g. `192.0.2.1:80` or `[2001:db8::1]:443`.
+pub fn field_socketaddr(address: SocketAddr) -> String {
+    address.to_string()
+}
+
 /// Create a syslog identifier from the current executable.
 ///
 /// Return `None` if we're unable to determine the name, e.g. because
@@ -102,15 +236,254 @@ pub fn current_exe_identifier() -> Option<String> {
     Some(executable.file_name()?.to_string_lossy().into_owned())
 }
 
-struct WriteKeyValues<'a>(&'a mut Vec<u8>);
+/// Get the CPU time of the current thread.
+///
+/// Return `None` if the thread CPU time clock is not available.
+fn thread_cpu_time() -> Option<Duration> {
+    // SAFETY: timespec is a plain C struct, for which all zeros is a valid value.
+    let mut time: libc::timespec = unsafe { std::mem::zeroed() };
+    // SAFETY: We pass a valid pointer to a timespec.
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    if result == 0 {
+        Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+    } else {
+        None
+    }
+}
+
+/// Get a small numeric id of the current thread.
+///
+/// Assign ids from a process-wide counter, starting at 1, on first use in
+/// each thread, so the id of a thread never changes.  For [`EncodeMode::Estimate`]
+/// don't assign an id to a thread which has none yet, but return the id it
+/// would get.
+fn current_thread_id(mode: EncodeMode) -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        // 0 denotes a thread without id
+        static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+    }
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            if mode == EncodeMode::Estimate {
+                return NEXT_ID.load(Ordering::Relaxed);
+            }
+            id.set(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+/// Get the time at which the current process started.
+///
+/// Compute the time from the boot time in `/proc/stat` and the start time of
+/// the process in clock ticks after boot from `/proc/self/stat`.  Return
+/// `None` if either is not available.
+fn process_start_time() -> Option<SystemTime> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let boot_time: u64 = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    let process_stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The process name in parentheses may contain spaces, so skip past it;
+    // the start time is the 22nd field, i.e. the 20th after the name
+    let start_ticks: u64 = process_stat
+        .rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
+    // SAFETY: sysconf has no preconditions.
+    let ticks_per_second = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) })
+        .ok()
+        .filter(|ticks| 0 < *ticks)?;
+    let since_boot = Duration::from_secs(start_ticks / ticks_per_second)
+        + Duration::from_secs(start_ticks % ticks_per_second) / ticks_per_second as u32;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(boot_time) + since_boot)
+}
+
+/// Count the open file descriptors of the current process.
+///
+/// Return `None` if `/proc/self/fd` is not available.
+fn open_fd_count() -> Option<usize> {
+    // Don't count the descriptor of the directory we're reading
+    Some(
+        std::fs::read_dir("/proc/self/fd")
+            .ok()?
+            .count()
+            .saturating_sub(1),
+    )
+}
+
+/// Append key-values as ` key=value` pairs to a message.
+///
+/// Quote values which are empty or contain whitespace or quotes.
+struct AppendKeyValues<'m>(&'m mut String);
+
+impl<'kvs> Visitor<'kvs> for AppendKeyValues<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        use std::fmt::Write;
+        let value = value.to_string();
+        let quote = value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"');
+        if quote {
+            write!(self.0, " {}={:?}", key, value)?;
+        } else {
+            write!(self.0, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect key-values with their escaped journal field names.
+///
+/// Collect at most `max` key-values, but count all.
+struct CollectKeyValues<'kvs, 'p> {
+    key_values: collision::EscapedKeyValues<'kvs>,
+    /// The original keys of all collected key-values.
+    keys: Vec<Key<'kvs>>,
+    max: usize,
+    total: usize,
+    /// Whether visiting the key-values of the record failed.
+    failed: bool,
+    /// What to do with non-ASCII characters in keys.
+    unicode_policy: UnicodeFieldPolicy,
+    /// Keys to use as field names without escaping.
+    passthrough: &'p HashSet<String>,
+}
+
+impl<'kvs, 'p> CollectKeyValues<'kvs, 'p> {
+    fn of(
+        record: &'kvs Record<'kvs>,
+        max: usize,
+        unicode_policy: UnicodeFieldPolicy,
+        passthrough: &'p HashSet<String>,
+    ) -> Self {
+        let mut collect = Self {
+            key_values: Vec::new(),
+            keys: Vec::new(),
+            max,
+            total: 0,
+            failed: false,
+            unicode_policy,
+            passthrough,
+        };
+        // Keep what we collected before the source failed
+        collect.failed = record.key_values().visit(&mut collect).is_err();
+        collect
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.keys.iter().map(|key| key.as_str()).collect()
+    }
+}
 
-impl<'a, 'kvs> Visitor<'kvs> for WriteKeyValues<'a> {
-    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
-        put_field_length_encoded(self.0, FieldName::WriteEscaped(key.as_str()), value);
+impl<'kvs> Visitor<'kvs> for CollectKeyValues<'kvs, '_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        self.total += 1;
+        if self.key_values.len() < self.max {
+            let name = if self.passthrough.contains(key.as_str()) {
+                key.as_str().as_bytes().to_vec()
+            } else {
+                escape_field_name(&self.unicode_policy.apply(key.as_str()))
+            };
+            self.key_values.push((name, value));
+            self.keys.push(key);
+        }
         Ok(())
     }
 }
 
+/// The key-values of a record, sorted by their escaped names.
+///
+/// Key-values with the same escaped name retain the order of the record.
+struct SortedKeyValues<'kvs> {
+    key_values: collision::EscapedKeyValues<'kvs>,
+    /// The number of key-values of the record, including those beyond the limit.
+    total: usize,
+    /// The number of key-values dropped because of the limit.
+    dropped: usize,
+    /// Whether visiting the key-values of the record failed.
+    failed: bool,
+}
+
+impl<'kvs> SortedKeyValues<'kvs> {
+    /// Collect the first `max` key-values of `record`, and resolve collisions by `policy`.
+    fn of(
+        record: &'kvs Record<'kvs>,
+        max: usize,
+        policy: CollisionPolicy,
+        unicode_policy: UnicodeFieldPolicy,
+        passthrough: &HashSet<String>,
+    ) -> Self {
+        let mut collect = CollectKeyValues::of(record, max, unicode_policy, passthrough);
+        let dropped = collect.total - collect.key_values.len();
+        let keys: Vec<&str> = collect.keys.iter().map(|key| key.as_str()).collect();
+        collision::resolve(policy, &keys, &mut collect.key_values);
+        collect.key_values.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self {
+            key_values: collect.key_values,
+            total: collect.total,
+            dropped,
+            failed: collect.failed,
+        }
+    }
+
+    /// Whether there's a key-value which escapes to the field `name`.
+    fn contains(&self, name: &[u8]) -> bool {
+        self.key_values
+            .binary_search_by(|(candidate, _)| candidate.as_slice().cmp(name))
+            .is_ok()
+    }
+}
+
+/// Whether encoding a record may change the state of the logger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EncodeMode {
+    /// Encode a record, and assign it a sequence number and a thread id.
+    Encode,
+    /// Only estimate the size of a record, without changing any state.
+    Estimate,
+}
+
+/// An encoded journal payload of a record.
+struct Encoded {
+    payload: Vec<u8>,
+    /// The number of key-values which failed to serialize.
+    kv_errors: usize,
+}
+
+/// The outcome of putting key-values into an entry.
+#[derive(Default)]
+struct PutKeyValues {
+    /// Whether the logger omitted key-values because of the entry budget.
+    budget_exceeded: bool,
+    /// The number of key-values which failed to format.
+    errors: usize,
+}
+
+/// A hook called with errors when sending records.
+type ErrorHook = Box<dyn Fn(&std::io::Error) + Send + Sync>;
+
+/// A function rewriting messages.
+type MessageTransform = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// A function mapping log levels to priorities.
+type PriorityMapping = Box<dyn Fn(Level) -> Priority + Send + Sync>;
+
+/// A writer to write records to as JSON.
+#[cfg(feature = "serde")]
+type JsonWriter = Arc<std::sync::Mutex<dyn Write + Send>>;
+
+/// Fields which the logger adds to a record, with well-formed names.
+type LoggerFields<'a> = &'a [(&'a str, &'a [u8])];
+
+/// A clock to timestamp records with.
+pub type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
 /// A systemd journal logger.
 ///
 /// ## Journal access
@@ -125,15 +498,72 @@ impl<'a, 'kvs> Visitor<'kvs> for WriteKeyValues<'a> {
 /// - `CODE_FILE`: The filename the log message originates from (see [`log::Record::file()`], only if present).
 /// - `CODE_LINE`: The line number the log message originates from (see [`log::Record::line()`], only if present).
 ///
-/// It also sets `SYSLOG_IDENTIFIER` if non-empty (see [`JournalLog::with_syslog_identifier`]).
+/// It also sets `SYSLOG_IDENTIFIER` if non-empty (see [`JournalLog::with_syslog_identifier`]
+/// and [`JournalLog::with_target_as_identifier`]).
+///
+/// Use [`JournalLog::with_standard_fields`] to omit some of these fields, or
+/// `TARGET`.
 ///
 /// Additionally it also adds the following non-standard fields:
 ///
-/// - `TARGET`: The target of the log record (see [`log::Record::target()`]).
+/// - `TARGET`: The target of the log record (see [`log::Record::target()`]);
+///   see [`JournalLog::with_logger_field_name`] to use a different field name.
 /// - `CODE_MODULE`: The module path of the log record (see [`log::Record::module_path()`], only if present).
+/// - `TRANSPORT`: The transport tag, if set (see [`JournalLog::with_transport_tag`]).
+/// - `COMM`: The name of the current thread, if enabled (see [`JournalLog::with_comm_field`]).
+/// - `RUST_THREAD_ID`: A numeric id of the current thread, if enabled
+///   (see [`JournalLog::with_thread_id_field`]).
+/// - `TASK_ID`: The id of the current tokio task, if enabled
+///   (see `JournalLog::with_task_id_field`, requires the `async` feature).
+/// - `THREAD_CPU_US`: The CPU time of the current thread in microseconds, if enabled
+///   (see [`JournalLog::with_cpu_time_field`]).
+/// - `OPEN_FDS`: The number of open file descriptors of the process, if enabled
+///   (see [`JournalLog::with_fd_count_field`]).
+/// - `KV_COUNT`: The number of key-values of the record, if enabled (see [`JournalLog::with_kv_count_field`]).
+/// - `MESSAGE_LEN`: The length of `MESSAGE` in bytes, if enabled (see [`JournalLog::with_message_len_field`]).
+/// - `SEQ`: The sequence number of the record, if enabled (see [`JournalLog::with_sequence_field`]).
+/// - `EUID` and `EGID`: The effective user and group ids of the process, if enabled
+///   (see [`JournalLog::with_credential_fields`]).
+/// - `MACHINE_ID`: The machine id from `/etc/machine-id`, if enabled
+///   (see [`JournalLog::with_machine_id`]).
+/// - `EXE`: The full path of the current executable, if enabled
+///   (see [`JournalLog::with_exe_path_field`]).
+/// - `GIT_COMMIT`: The git commit of the application, if set (see [`JournalLog::with_git_commit`]).
+/// - `BUILD_PROFILE`: The build profile of this crate, if enabled
+///   (see [`JournalLog::with_build_profile_field`]).
+/// - Fields read from environment variables, if set (see [`JournalLog::with_env_field`]).
+/// - `SAMPLED` and `SAMPLE_RATE`: Whether and how the logger sampled the record, if
+///   enabled (see [`JournalLog::with_sampling`]).
+/// - `TRACE_ID` and `SPAN_ID`: The ids of the current OpenTelemetry span, if enabled
+///   (see `JournalLog::with_current_span_context`, requires the `otel` feature).
 ///
 /// [journal fields]: https://www.freedesktop.org/software/systemd/man/systemd.journal-fields.html
 ///
+/// ## Field order
+///
+/// While journald doesn't care about the order of fields, the logger writes
+/// fields in a fixed order to make its output deterministic:
+///
+/// 1. `PRIORITY` and `MESSAGE`,
+/// 2. `SYSLOG_IDENTIFIER` and `SYSLOG_PID`,
+/// 3. `CODE_FILE`, `CODE_LINE` and `CODE_MODULE`,
+/// 4. `TARGET` and all other non-standard fields in the order listed above,
+/// 5. extra fields of the logger, in the order they were added, and scoped
+///    fields (see [`JournalLog::with_scoped_fields`]),
+/// 6. default fields of the logger not overridden by the record,
+/// 7. structured key-values of the record, sorted by their escaped name,
+///    `FIELDS_DROPPED` if the logger dropped key-values (see
+///    [`JournalLog::with_max_fields`]), `BUDGET_EXCEEDED` if key-values
+///    exceeded the entry budget (see [`JournalLog::with_max_entry_bytes`]),
+///    and `KV_ERRORS` with the number of key-values which failed to
+///    serialize, if any,
+/// 8. `SYSLOG_TIMESTAMP` and `SOURCE_REALTIME_TIMESTAMP`, if the logger has a
+///    clock (see [`JournalLog::with_clock`]) or for [`JournalLog::log_at`], and
+/// 9. `ENTRY_SIZE` and `ENTRY_SIZE_WARN` for large entries, if enabled (see
+///    [`JournalLog::with_size_warning_field`]).
+///
+/// See [`JournalLog::journal_encode`] to obtain the encoded fields of a record.
+///
 /// ## Log levels and Priorities
 ///
 /// [`log::Level`] gets mapped to journal (syslog) priorities as follows:
@@ -144,13 +574,18 @@ impl<'a, 'kvs> Visitor<'kvs> for WriteKeyValues<'a> {
 /// - [`Level::Debug`] → `6` (info)
 /// - [`Level::Trace`] → `7` (debug)
 ///
-/// Higher priorities (crit, alert, and emerg) are not used.
+/// Higher priorities (crit, alert, and emerg) are not used, unless the record
+/// has a `severity` key-value whose value names a syslog severity, i.e. one
+/// of `emerg`, `alert`, `crit`, `err`, `warning`, `notice`, `info`, or `debug`.
+/// Such a key-value overrides the priority of the log level; the logger
+/// ignores other values.
 ///
 /// ## Custom fields and structured record fields
 ///
 /// In addition to these fields the logger also adds all structures key-values
 /// (see [`log::Record::key_values`]) from each log record as journal fields,
-/// and also supports global extra fields via [`Self::with_extra_fields`].
+/// and also supports global extra fields via [`Self::with_extra_fields`],
+/// and default fields which records can override via [`Self::with_default_fields`].
 ///
 /// Journald allows only ASCII uppercase letters, ASCII digits, and the
 /// underscore in field names, and limits field names to 64 bytes.  See upstream's
@@ -162,13 +597,22 @@ impl<'a, 'kvs> Visitor<'kvs> for WriteKeyValues<'a> {
 ///
 /// - If the key is entirely empty, use `EMPTY`.
 /// - Transform the entire value to ASCII uppercase.
-/// - Replace all invalid characters with underscore.
+/// - Replace all invalid characters with underscore.  For keys of key-values
+///   [`JournalLog::with_unicode_field_policy`] can transliterate non-ASCII
+///   letters instead.
 /// - If the key starts with an underscore or digit, which is not permitted,
 ///   prepend `ESCAPED_`.
 /// - Cap the result to 64 bytes.
 ///
 /// [jfv]: https://github.com/systemd/systemd/blob/a8b53f4f1558b17169809effd865232580e4c4af/src/libsystemd/sd-journal/journal-file.c#L1698
 ///
+/// ## Thread safety
+///
+/// The logger is `Send` and `Sync`, so it can be shared across threads, e.g.
+/// in an [`Arc`], and all threads can log through it concurrently.  Each
+/// record goes to the journal in a single datagram, so records of different
+/// threads never interleave.
+///
 /// # Errors
 ///
 /// The logger tries to connect to journald when constructed, to provide early
@@ -177,66 +621,136 @@ impl<'a, 'kvs> Visitor<'kvs> for WriteKeyValues<'a> {
 ///
 /// Later on, the logger simply ignores any errors when sending log records to
 /// journald, simply because the log interface does not expose faillible operations.
+/// Use [`JournalLog::on_error`] to get notified about these errors.
 pub struct JournalLog {
     /// The journald client
     client: JournalClient,
-    /// Preformatted extra fields to be appended to every log message.
-    extra_fields: Vec<u8>,
-    /// The syslog identifier.
-    syslog_identifier: String,
-}
-
-fn record_payload(syslog_identifier: &str, record: &Record) -> Vec<u8> {
-    use FieldName::*;
-    let mut buffer = Vec::with_capacity(1024);
-    // Write standard fields. Numeric fields can't contain new lines so we
-    // write them directly, everything else goes through the put functions
-    // for property mangling and length-encoding
-    let priority = match record.level() {
-        Level::Error => b"3",
-        Level::Warn => b"4",
-        Level::Info => b"5",
-        Level::Debug => b"6",
-        Level::Trace => b"7",
-    };
-    put_field_bytes(&mut buffer, WellFormed("PRIORITY"), priority);
-    put_field_length_encoded(&mut buffer, WellFormed("MESSAGE"), record.args());
-    // Syslog compatibility fields
-    writeln!(&mut buffer, "SYSLOG_PID={}", std::process::id()).unwrap();
-    if !syslog_identifier.is_empty() {
-        put_field_bytes(
-            &mut buffer,
-            WellFormed("SYSLOG_IDENTIFIER"),
-            syslog_identifier.as_bytes(),
-        );
-    }
-    if let Some(file) = record.file() {
-        put_field_bytes(&mut buffer, WellFormed("CODE_FILE"), file.as_bytes());
-    }
-    if let Some(module) = record.module_path() {
-        put_field_bytes(&mut buffer, WellFormed("CODE_MODULE"), module.as_bytes());
-    }
-    if let Some(line) = record.line() {
-        writeln!(&mut buffer, "CODE_LINE={}", line).unwrap();
-    }
-    put_field_bytes(
-        &mut buffer,
-        WellFormed("TARGET"),
-        record.target().as_bytes(),
-    );
-    // Put all structured values of the record
-    record
-        .key_values()
-        .visit(&mut WriteKeyValues(&mut buffer))
-        .unwrap();
-    buffer
+    /// The transport to send entries through instead of the client, if any.
+    transport: Option<Arc<dyn JournalTransport>>,
+    /// The settings which [`Self::reconfigure`] replaces.
+    settings: config::SharedSettings,
+    /// The value of the `TRANSPORT` field, if any.
+    transport_tag: Option<String>,
+    /// Whether to add the ids of the current OpenTelemetry span.
+    #[cfg(feature = "otel")]
+    current_span_context: bool,
+    /// Whether to also write records to standard error.
+    tee_stderr: bool,
+    /// Whether to also write records to the kernel log.
+    kmsg_tee: bool,
+    /// The writer to also write records to as JSON, if any.
+    #[cfg(feature = "serde")]
+    json_tee: Option<JsonWriter>,
+    /// The maximum length of `MESSAGE` in bytes, if any.
+    max_message_len: Option<usize>,
+    /// A function to rewrite messages with, if any.
+    message_transform: Option<MessageTransform>,
+    /// Escaped names and preformatted fields to add unless a record overrides them.
+    default_fields: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Whether the syslog identifier was set explicitly.
+    explicit_syslog_identifier: bool,
+    /// Whether to use the record target as syslog identifier.
+    target_as_identifier: bool,
+    /// A hook to call when sending a record fails.
+    on_error: Option<ErrorHook>,
+    /// Whether to write boolean key-values as `1` and `0`.
+    numeric_booleans: bool,
+    /// If set, write the thread name as `COMM`, falling back to this process name.
+    comm_fallback: Option<String>,
+    /// What to do with records with an empty message.
+    empty_message_policy: EmptyMessagePolicy,
+    /// Whether to remove a trailing newline from `MESSAGE`.
+    trim_trailing_newline: bool,
+    /// Whether to add the number of key-values as `KV_COUNT` field.
+    kv_count_field: bool,
+    /// Whether to add the length of the message as `MESSAGE_LEN` field.
+    message_len_field: bool,
+    /// Whether to append key-values to `MESSAGE`.
+    append_kv_to_message: bool,
+    /// The maximum number of key-values to write per record, if any.
+    max_fields: Option<usize>,
+    /// The size in bytes above which to stop adding key-values, if any.
+    max_entry_bytes: Option<usize>,
+    /// Preformatted `EUID` and `EGID` fields, if enabled.
+    credential_fields: Vec<u8>,
+    /// The preformatted `MACHINE_ID` field, if enabled.
+    machine_id_field: Vec<u8>,
+    /// The preformatted `EXE` field, if enabled.
+    exe_path_field: Vec<u8>,
+    /// The value of the `GIT_COMMIT` field, if any.
+    git_commit: Option<String>,
+    /// The version of the application for the startup banner, if any.
+    version: Option<String>,
+    /// Whether to add the build profile as `BUILD_PROFILE`.
+    build_profile_field: bool,
+    /// Field names and the environment variables to read their values from.
+    env_fields: Vec<(String, String)>,
+    /// Names of default fields as given, for validation.
+    default_field_names: Vec<String>,
+    /// The separator for merged fields, if enabled.
+    field_merge_separator: Option<String>,
+    /// Whether to add a numeric id of the current thread as `RUST_THREAD_ID`.
+    thread_id_field: bool,
+    /// Whether to add the id of the current tokio task as `TASK_ID`.
+    #[cfg(feature = "async")]
+    task_id_field: bool,
+    /// Whether to add the CPU time of the current thread as `THREAD_CPU_US`.
+    cpu_time_field: bool,
+    /// Whether to add the number of open file descriptors as `OPEN_FDS`.
+    fd_count_field: bool,
+    /// The clock to timestamp records with, if any.
+    clock: Option<Clock>,
+    /// The number of records suppressed so far.
+    suppressed: AtomicU64,
+    /// The most recent entries, if enabled.
+    ring_buffer: Option<ring::RingBuffer>,
+    /// A function to redact fields with before sending, if any.
+    field_redactor: Option<redact::Redactor>,
+    /// The entry size above which to add size warning fields, if any.
+    size_warning_threshold: Option<usize>,
+    /// What to do with distinct keys which escape to the same field name.
+    collision_policy: CollisionPolicy,
+    /// Whether to fall back to syslog if the journal is unavailable.
+    syslog_fallback: bool,
+    /// The path of the syslog socket to fall back to.
+    syslog_path: PathBuf,
+    /// The mapping of log levels to priorities, if not the default.
+    priority_mapping: Option<PriorityMapping>,
+    /// The least severe priority to write, if any.
+    priority_floor: Option<Priority>,
+    /// The most severe priorities to write by target prefix, longest prefix first.
+    target_priority_ceilings: Vec<(String, Priority)>,
+    /// The maximum level of records to send if not connected to the journal, if any.
+    unattached_level: Option<Level>,
+    /// Write only lean standard fields for records less severe than this level, if any.
+    lean_below: Option<Level>,
+    /// How to sample verbose records, if at all.
+    sampler: Option<sampling::Sampler>,
+    /// How to route records to journal namespaces, if at all.
+    namespace_router: Option<namespace::NamespaceRouter>,
+    /// What to do with key-values with empty values.
+    empty_field_policy: EmptyFieldPolicy,
+    /// What to do with non-ASCII characters in keys of key-values.
+    unicode_field_policy: UnicodeFieldPolicy,
+    /// Keys which the logger uses as field names without escaping.
+    passthrough_fields: HashSet<String>,
+    /// Syslog identifiers by target prefix, longest prefix first.
+    identifier_map: Vec<(String, String)>,
+    /// Whether to remove the crate name from `CODE_MODULE`.
+    module_strip_crate: bool,
+    /// Whether to add a sequence number as `SEQ`.
+    sequence_field: bool,
+    /// The sequence number of the next record.
+    sequence: AtomicU64,
 }
 
 impl JournalLog {
     /// Create a journal log instance with a default syslog identifier.
-    pub fn new() -> std::io::Result<Self> {
-        let logger = Self::empty()?;
-        Ok(logger.with_syslog_identifier(current_exe_identifier().unwrap_or_default()))
+    pub fn new() -> Result<Self, Error> {
+        let mut logger = Self::empty()?;
+        // Set the field directly, because this identifier isn't explicit
+        logger.settings_mut().syslog_identifier = current_exe_identifier().unwrap_or_default();
+        Ok(logger)
     }
 
     /// Create an empty journal log instance, with no extra fields and no syslog
@@ -244,12 +758,100 @@ impl JournalLog {
     ///
     /// See [`Self::with_syslog_identifier`] and [`Self::with_extra_fields`] to
     /// set either.  It's recommended to at least set the syslog identifier.
-    pub fn empty() -> std::io::Result<Self> {
-        Ok(Self {
-            client: JournalClient::new()?,
-            extra_fields: Vec::new(),
-            syslog_identifier: String::new(),
-        })
+    pub fn empty() -> Result<Self, Error> {
+        let client = JournalClient::new().map_err(Error::SocketUnavailable)?;
+        Ok(Self::with_client(client))
+    }
+
+    /// Create a journal log instance with a default syslog identifier, without checking the journal.
+    ///
+    /// Unlike [`Self::new`] this constructor doesn't open and check the journal
+    /// socket; instead the logger opens the socket when sending the first
+    /// record.  Hence this constructor never fails, which suits environments
+    /// where the journal is always present, such as systemd services.  Sending
+    /// records fails if journald is not available (see [`Self::journal_send`]).
+    pub fn new_unchecked() -> Self {
+        let mut logger = Self::with_client(JournalClient::unchecked());
+        // Set the field directly, because this identifier isn't explicit
+        logger.settings_mut().syslog_identifier = current_exe_identifier().unwrap_or_default();
+        logger
+    }
+
+    /// Create an empty journal log instance which sends through `client`.
+    fn with_client(client: JournalClient) -> Self {
+        Self {
+            client,
+            transport: None,
+            settings: Arc::new(RwLock::new(Arc::new(config::Settings::new()))),
+            transport_tag: None,
+            #[cfg(feature = "otel")]
+            current_span_context: false,
+            tee_stderr: false,
+            kmsg_tee: false,
+            #[cfg(feature = "serde")]
+            json_tee: None,
+            max_message_len: None,
+            message_transform: None,
+            default_fields: Vec::new(),
+            explicit_syslog_identifier: false,
+            target_as_identifier: false,
+            on_error: None,
+            numeric_booleans: false,
+            comm_fallback: None,
+            empty_message_policy: EmptyMessagePolicy::default(),
+            trim_trailing_newline: false,
+            kv_count_field: false,
+            message_len_field: false,
+            append_kv_to_message: false,
+            max_fields: None,
+            max_entry_bytes: None,
+            credential_fields: Vec::new(),
+            machine_id_field: Vec::new(),
+            exe_path_field: Vec::new(),
+            git_commit: None,
+            version: None,
+            build_profile_field: false,
+            env_fields: Vec::new(),
+            default_field_names: Vec::new(),
+            field_merge_separator: None,
+            thread_id_field: false,
+            #[cfg(feature = "async")]
+            task_id_field: false,
+            cpu_time_field: false,
+            fd_count_field: false,
+            clock: None,
+            suppressed: AtomicU64::new(0),
+            ring_buffer: None,
+            field_redactor: None,
+            size_warning_threshold: None,
+            collision_policy: CollisionPolicy::default(),
+            syslog_fallback: false,
+            syslog_path: PathBuf::from(syslog::SYSLOG_PATH),
+            priority_mapping: None,
+            priority_floor: None,
+            target_priority_ceilings: Vec::new(),
+            unattached_level: None,
+            lean_below: None,
+            sampler: None,
+            namespace_router: None,
+            empty_field_policy: EmptyFieldPolicy::Keep,
+            unicode_field_policy: UnicodeFieldPolicy::Replace,
+            passthrough_fields: HashSet::new(),
+            identifier_map: Vec::new(),
+            module_strip_crate: false,
+            sequence_field: false,
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the current settings of this logger.
+    fn settings(&self) -> Arc<config::Settings> {
+        sync::read_ignoring_poison(&self.settings).clone()
+    }
+
+    /// Get the settings of this logger for changing them.
+    fn settings_mut(&mut self) -> config::SettingsMut<'_> {
+        config::SettingsMut(sync::write_ignoring_poison(&self.settings))
     }
 
     /// Install this logger globally.
@@ -283,12 +885,16 @@ impl JournalLog {
     /// ## Restrictions on values
     ///
     /// There are no restrictions on the value.
+    ///
+    /// ## Types of names and values
+    ///
+    /// `name` and `value` may be of any type which references a string or
+    /// bytes respectively, e.g. static strs, [`String`]s, or [`Cow`]s.  The
+    /// logger encodes fields right away and only borrows `name` and `value`
+    /// for this, so none of these types need additional allocations.
     pub fn add_extra_field<K: AsRef<str>, V: AsRef<[u8]>>(mut self, name: K, value: V) -> Self {
-        put_field_bytes(
-            &mut self.extra_fields,
-            FieldName::WriteEscaped(name.as_ref()),
-            value.as_ref(),
-        );
+        self.settings_mut()
+            .add_extra_field(name.as_ref(), value.as_ref());
         self
     }
 
@@ -303,7 +909,7 @@ impl JournalLog {
         K: AsRef<str>,
         V: AsRef<[u8]>,
     {
-        self.extra_fields.clear();
+        self.settings_mut().clear_extra_fields();
         let mut logger = self;
         for (name, value) in extra_fields {
             logger = logger.add_extra_field(name, value);
@@ -311,6 +917,375 @@ impl JournalLog {
         logger
     }
 
+    /// Whether to merge extra fields and key-values of the same name.
+    ///
+    /// By default the logger writes both an extra field and a key-value of a
+    /// record which escape to the same name, so the journal entry has multiple
+    /// values for this field.  If enabled the logger instead writes a single
+    /// field with the value of the extra field followed by the values of all
+    /// key-values of the same name, separated by `,` (see
+    /// [`Self::with_field_merge_separator`]).
+    ///
+    /// Disabled by default.
+    pub fn with_field_merge(mut self, enabled: bool) -> Self {
+        self.field_merge_separator = if enabled { Some(",".to_string()) } else { None };
+        self
+    }
+
+    /// Merge extra fields and key-values of the same name with `separator`.
+    ///
+    /// Enable merging fields (see [`Self::with_field_merge`]), and separate
+    /// merged values with the given `separator`.
+    pub fn with_field_merge_separator(mut self, separator: impl Into<String>) -> Self {
+        self.field_merge_separator = Some(separator.into());
+        self
+    }
+
+    /// Add `fields` to all records logged on the current thread while running `f`.
+    ///
+    /// Run `f` and return its result; while `f` runs all records logged on
+    /// the current thread get the given `fields`, after the extra fields of the
+    /// logger.  Fields follow the same escaping rules as extra fields.  This is
+    /// useful to add request-scoped context to records.
+    ///
+    /// Scopes nest, like spans: records get the fields of all enclosing
+    /// scopes, and if scopes have fields of the same name after escaping, the
+    /// innermost scope wins.  The logger removes `fields` when `f` returns or
+    /// panics.  Note that scoped fields apply to all [`JournalLog`] instances
+    /// on the current thread.
+    pub fn with_scoped_fields<R>(&self, fields: Vec<(String, String)>, f: impl FnOnce() -> R) -> R {
+        scoped_fields::with_scoped_fields(&fields, f)
+    }
+
+    /// Set default fields to be added to every log entry without a key-value of the same name.
+    ///
+    /// Unlike [`Self::with_extra_fields`] the logger omits a default field if the
+    /// record has a key-value with the same name after escaping, so records can
+    /// override default fields.  Default fields follow the same escaping rules
+    /// as extra fields.
+    ///
+    /// Remove all previously set default fields.
+    pub fn with_default_fields(mut self, fields: Vec<(String, String)>) -> Self {
+        self.default_field_names = fields.iter().map(|(name, _)| name.clone()).collect();
+        self.default_fields = fields
+            .into_iter()
+            .map(|(name, value)| {
+                let mut field = Vec::new();
+                put_field_bytes(&mut field, FieldName::WriteEscaped(&name), value.as_bytes());
+                (escape_field_name(&name), field)
+            })
+            .collect();
+        self
+    }
+
+    /// Whether to write boolean key-values of records as `1` and `0`.
+    ///
+    /// If enabled the logger writes `true` as `1` and `false` as `0`, which
+    /// makes it easier to match on these fields, e.g. `journalctl FOO=1`.
+    ///
+    /// Disabled by default, i.e. the logger writes `true` and `false`.
+    pub fn with_numeric_booleans(mut self, enabled: bool) -> Self {
+        self.numeric_booleans = enabled;
+        self
+    }
+
+    /// Set which standard fields to write.
+    ///
+    /// By default the logger writes all standard fields listed in
+    /// [`JournalLog`]; this method allows to omit some of them, e.g. to reduce
+    /// the size of journal entries.  It's not recommended to omit `PRIORITY`
+    /// or `MESSAGE`.
+    pub fn with_standard_fields(mut self, fields: StandardFields) -> Self {
+        self.settings_mut().standard_fields = fields;
+        self
+    }
+
+    /// Write only a few standard fields for records less severe than `level`.
+    ///
+    /// For records less severe than `level` the logger only writes the
+    /// standard fields `PRIORITY`, `MESSAGE` and `TARGET`, and omits
+    /// `SYSLOG_IDENTIFIER`, `SYSLOG_PID` and all `CODE_` fields, e.g. with
+    /// [`Level::Info`] debug and trace records are lean, while info records,
+    /// warnings and errors have all standard fields.  This reduces the
+    /// overhead of high-volume debug logging, while keeping severe records
+    /// complete.
+    ///
+    /// This only affects standard fields; it doesn't remove fields omitted by
+    /// [`Self::with_standard_fields`], and the logger still writes key-values
+    /// and extra fields for lean records.
+    pub fn with_lean_below(mut self, level: Level) -> Self {
+        self.lean_below = Some(level);
+        self
+    }
+
+    /// Set the git commit of the application.
+    ///
+    /// The logger writes `commit` verbatim in the custom `GIT_COMMIT` field, to
+    /// tell which build of the application emitted a log record.  Applications
+    /// typically pass a constant generated by their build script.
+    pub fn with_git_commit(mut self, commit: impl Into<String>) -> Self {
+        self.git_commit = Some(commit.into());
+        self
+    }
+
+    /// Set the version of the application.
+    ///
+    /// The logger writes `version` in the `VERSION` field of the startup
+    /// banner (see [`Self::log_startup`]).  Applications typically pass
+    /// `env!("CARGO_PKG_VERSION")`.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Whether to add the build profile as `BUILD_PROFILE` field.
+    ///
+    /// If enabled the logger writes `debug` if debug assertions are enabled,
+    /// and `release` otherwise, to tell debug from release builds in the
+    /// journal.  The value is fixed at compile time, and reflects the build
+    /// profile of this crate, which normally matches the application's.
+    ///
+    /// Disabled by default.
+    pub fn with_build_profile_field(mut self, enabled: bool) -> Self {
+        self.build_profile_field = enabled;
+        self
+    }
+
+    /// Add the value of the environment variable `env_var` as `field_name`.
+    ///
+    /// The logger reads `env_var` for every record, and omits the field if
+    /// `env_var` is unset.  The logger escapes `field_name` like the names of
+    /// extra fields (see [`Self::add_extra_field`]).  Applications can use
+    /// this to add e.g. the name of their container or pod to every record.
+    ///
+    /// Call this method multiple times to add several fields.
+    pub fn with_env_field(mut self, field_name: String, env_var: String) -> Self {
+        self.env_fields.push((field_name, env_var));
+        self
+    }
+
+    /// Whether to add the name of the current thread as `COMM` field.
+    ///
+    /// If enabled the logger adds the name of the thread which logs a record
+    /// in the custom `COMM` field, or the name of the current executable (see
+    /// [`current_exe_identifier`]) for unnamed threads.  Unlike the trusted
+    /// `_COMM` field, which journald sets to the process name, this field
+    /// tells threads apart.
+    ///
+    /// Disabled by default.
+    pub fn with_comm_field(mut self, enabled: bool) -> Self {
+        self.comm_fallback = if enabled {
+            Some(current_exe_identifier().unwrap_or_default())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Whether to remove the crate name from `CODE_MODULE`.
+    ///
+    /// If enabled the logger removes the leading crate name from the module
+    /// path of a record, e.g. it writes `net::http` instead of
+    /// `my_app::net::http`.  For records from the root module of a crate the
+    /// logger omits `CODE_MODULE`.
+    ///
+    /// Disabled by default.
+    pub fn with_module_strip_crate(mut self, enabled: bool) -> Self {
+        self.module_strip_crate = enabled;
+        self
+    }
+
+    /// Write the target of records to the field `name` instead of `TARGET`.
+    ///
+    /// Some log processing pipelines expect a different field for the logger
+    /// name, e.g. `LOGGER`.
+    ///
+    /// # Errors
+    ///
+    /// Fail with [`Error::InvalidFieldName`] if `name` is not a valid journal
+    /// field name, i.e. if it's empty, longer than 64 bytes, contains other
+    /// characters than ASCII uppercase letters, numbers and underscores, or
+    /// doesn't start with a letter.
+    pub fn with_logger_field_name(mut self, name: String) -> Result<Self, Error> {
+        if !is_valid_field_name(&name) {
+            return Err(Error::InvalidFieldName(name));
+        }
+        self.settings_mut().target_field_name = name;
+        Ok(self)
+    }
+
+    /// Whether to add a numeric id of the current thread as `RUST_THREAD_ID` field.
+    ///
+    /// [`std::thread::ThreadId`] has no stable numeric representation, so the
+    /// logger instead numbers threads in the order they first log a record,
+    /// starting at 1.  The id of a thread stays the same for its lifetime, and
+    /// is shared by all [`JournalLog`] instances in the process.
+    ///
+    /// Disabled by default.
+    pub fn with_thread_id_field(mut self, enabled: bool) -> Self {
+        self.thread_id_field = enabled;
+        self
+    }
+
+    /// Whether to add the id of the current tokio task as `TASK_ID` field.
+    ///
+    /// Use [`tokio::task::try_id`] to get the id of the task which logs a
+    /// record, to tell apart records of concurrent tasks on the same thread.
+    /// The logger omits the field outside of a task, e.g. in
+    /// [`tokio::runtime::Runtime::block_on`] or on threads not managed by
+    /// tokio.
+    ///
+    /// Disabled by default.  Requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub fn with_task_id_field(mut self, enabled: bool) -> Self {
+        self.task_id_field = enabled;
+        self
+    }
+
+    /// Whether to add the CPU time of the current thread as `THREAD_CPU_US` field.
+    ///
+    /// If enabled the logger adds the CPU time the thread logging a record
+    /// consumed so far in microseconds (see `CLOCK_THREAD_CPUTIME_ID` in
+    /// `clock_gettime(2)`), for profiling.  The logger omits the field if the
+    /// clock is not available.
+    ///
+    /// Disabled by default.
+    pub fn with_cpu_time_field(mut self, enabled: bool) -> Self {
+        self.cpu_time_field = enabled;
+        self
+    }
+
+    /// Whether to add the number of open file descriptors as `OPEN_FDS` field.
+    ///
+    /// If enabled the logger counts the open file descriptors of the process
+    /// in `/proc/self/fd` for every record, to help diagnose descriptor leaks.
+    /// The logger omits the field if `/proc` is not available.
+    ///
+    /// Counting descriptors is relatively expensive, so this is best used with
+    /// a dedicated logger for periodic health records.
+    ///
+    /// Disabled by default.
+    pub fn with_fd_count_field(mut self, enabled: bool) -> Self {
+        self.fd_count_field = enabled;
+        self
+    }
+
+    /// Whether to add the number of key-values of a record as `KV_COUNT` field.
+    ///
+    /// This helps to debug the coverage of structured logging.
+    ///
+    /// Disabled by default.
+    pub fn with_kv_count_field(mut self, enabled: bool) -> Self {
+        self.kv_count_field = enabled;
+        self
+    }
+
+    /// Whether to add the length of `MESSAGE` in bytes as `MESSAGE_LEN` field.
+    ///
+    /// The length counts bytes, not characters, of the message as written,
+    /// i.e. after transforming and truncating it.  The logger omits the field
+    /// if it doesn't write `MESSAGE` (see [`Self::with_standard_fields`]).
+    /// This helps a quick analysis of log volume.
+    ///
+    /// Disabled by default.
+    pub fn with_message_len_field(mut self, enabled: bool) -> Self {
+        self.message_len_field = enabled;
+        self
+    }
+
+    /// Whether to append the key-values of records to `MESSAGE`.
+    ///
+    /// If enabled the logger appends ` key=value` for every key-value of a
+    /// record to its message, in the order of the record, and quotes values
+    /// which are empty or contain whitespace or quotes, e.g. `login user=alice
+    /// reason="bad password"`.  The logger still writes all key-values as
+    /// separate fields.  This helps consumers which only read `MESSAGE`.
+    ///
+    /// The logger appends key-values after transforming the message (see
+    /// [`Self::with_message_transform`]) and before truncating it (see
+    /// [`Self::with_max_message_len`]).
+    ///
+    /// Disabled by default.
+    pub fn with_append_kv_to_message(mut self, enabled: bool) -> Self {
+        self.append_kv_to_message = enabled;
+        self
+    }
+
+    /// Whether to add a sequence number as `SEQ` field.
+    ///
+    /// If enabled the logger numbers the records it encodes, starting at 0
+    /// for the first record after creating the logger.  Gaps or reordered
+    /// numbers in the journal indicate dropped or reordered entries.
+    ///
+    /// Note that records which the logger discards on purpose, e.g. because
+    /// of sampling, don't get a number, but records which it fails to send do.
+    ///
+    /// Disabled by default.
+    pub fn with_sequence_field(mut self, enabled: bool) -> Self {
+        self.sequence_field = enabled;
+        self
+    }
+
+    /// Whether to add the effective user and group ids as `EUID` and `EGID` fields.
+    ///
+    /// The logger gets these ids once when enabling these fields.  Unlike the
+    /// trusted `_UID` and `_GID` fields, which journald sets to the real ids,
+    /// these fields show the credentials of e.g. setuid programs.
+    ///
+    /// Disabled by default.
+    pub fn with_credential_fields(mut self, enabled: bool) -> Self {
+        self.credential_fields.clear();
+        if enabled {
+            // SAFETY: geteuid and getegid always succeed.
+            let (euid, egid) = unsafe { (libc::geteuid(), libc::getegid()) };
+            writeln!(self.credential_fields, "EUID={}", euid).unwrap();
+            writeln!(self.credential_fields, "EGID={}", egid).unwrap();
+        }
+        self
+    }
+
+    /// Whether to add the machine id from `/etc/machine-id` as `MACHINE_ID` field.
+    ///
+    /// The logger reads the machine id once when enabling this field, and
+    /// omits the field if reading fails.  Unlike the trusted `_MACHINE_ID`
+    /// field this field survives forwarding entries to other machines, e.g.
+    /// with `systemd-journal-upload`, which helps to correlate entries from a
+    /// fleet of machines.
+    ///
+    /// Disabled by default.
+    pub fn with_machine_id(mut self, enabled: bool) -> Self {
+        self.machine_id_field.clear();
+        if let Some(machine_id) = enabled
+            .then(|| std::fs::read_to_string("/etc/machine-id").ok())
+            .flatten()
+            .filter(|id| !id.trim().is_empty())
+        {
+            writeln!(self.machine_id_field, "MACHINE_ID={}", machine_id.trim()).unwrap();
+        }
+        self
+    }
+
+    /// Whether to add the full path of the current executable as `EXE` field.
+    ///
+    /// The logger gets the path from [`std::env::current_exe`] once when
+    /// enabling this field, and omits the field if that fails.  Unlike
+    /// `SYSLOG_IDENTIFIER`, which only holds the file name, this field tells
+    /// apart executables of the same name, and unlike the trusted `_EXE`
+    /// field it survives forwarding entries to other machines.
+    ///
+    /// Disabled by default.
+    pub fn with_exe_path_field(mut self, enabled: bool) -> Self {
+        self.exe_path_field.clear();
+        if let Some(exe) = enabled.then(|| std::env::current_exe().ok()).flatten() {
+            put_field_bytes(
+                &mut self.exe_path_field,
+                FieldName::WellFormed("EXE"),
+                exe.as_os_str().as_bytes(),
+            );
+        }
+        self
+    }
+
     /// Set the given syslog identifier for this logger.
     ///
     /// The logger writes this string in the `SYSLOG_IDENTIFIER` field, which
@@ -319,56 +1294,1928 @@ impl JournalLog {
     /// Use [`current_exe_identifier()`] to obtain the standard identifier for
     /// the current executable.
     pub fn with_syslog_identifier(mut self, identifier: String) -> Self {
-        self.syslog_identifier = identifier;
+        self.settings_mut().syslog_identifier = identifier;
+        self.explicit_syslog_identifier = true;
         self
     }
 
-    /// Get the complete journal payload for `record`, including extra fields
-    /// from this logger.
-    fn record_payload(&self, record: &Record) -> Vec<u8> {
-        let mut payload = record_payload(&self.syslog_identifier, record);
-        payload.extend_from_slice(&self.extra_fields);
-        payload
-    }
-
-    /// Send a single log record to the journal.
+    /// Use the kernel's name of the current process as syslog identifier.
     ///
-    /// Extract all fields (standard and custom) from `record` (`see [`JournalLog`]),
-    /// append all `extra_fields` given to this logger, and send the result to
-    /// journald.
-    pub fn journal_send(&self, record: &Record) -> std::io::Result<()> {
-        let _ = self.client.send_payload(&self.record_payload(record))?;
-        Ok(())
+    /// Use the process name from `/proc/self/comm` (see [`comm_identifier()`])
+    /// instead of the name of the executable (see [`current_exe_identifier()`]).
+    /// If reading the process name fails fall back to the name of the
+    /// executable.
+    ///
+    /// Like [`Self::with_syslog_identifier`] this sets an explicit identifier.
+    pub fn with_identifier_from_comm(self) -> Self {
+        let identifier = comm_identifier()
+            .or_else(current_exe_identifier)
+            .unwrap_or_default();
+        self.with_syslog_identifier(identifier)
     }
-}
 
-/// The [`Log`] interface for [`JournalLog`].
-impl Log for JournalLog {
-    /// Whether this logger is enabled.
+    /// Whether to use the target of log records as syslog identifier.
+    ///
+    /// If enabled the logger writes the target of each record to the
+    /// `SYSLOG_IDENTIFIER` field instead of the name of the current executable,
+    /// unless an identifier was set explicitly with [`Self::with_syslog_identifier`].
     ///
-    /// Always returns `true`.
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    /// Disabled by default.
+    pub fn with_target_as_identifier(mut self, enabled: bool) -> Self {
+        self.target_as_identifier = enabled;
+        self
     }
 
-    /// Send the given `record` to the systemd journal.
+    /// Use different syslog identifiers for different targets.
     ///
-    /// # Errors
+    /// `map` contains pairs of target prefixes and identifiers.  For each
+    /// record the logger uses the identifier of the longest prefix of the
+    /// record's target in `map`, and falls back to the identifier of the logger
+    /// if no prefix matches.  Identifiers from `map` take precedence over the
+    /// identifier of the logger, including explicit identifiers and
+    /// [`Self::with_target_as_identifier`].
     ///
-    /// Ignore any errors which occur when sending `record` to journald because
-    /// we cannot reasonably handle them at this place.
+    /// Prefixes match literally, so the prefix `app::db` matches the targets
+    /// `app::db::pool` and `app::dbus` alike.
     ///
-    /// See [`JournalLog::journal_send`] for a function which returns any error
-    /// which might have occurred while sending the `record` to the journal.
-    fn log(&self, record: &Record) {
-        // We can't really handle errors here, so simply discard them.
-        // The alternative would be to panic, but a failed logging call should
-        // not bring the entire process down.
-        let _ = self.journal_send(record);
+    /// Remove all previously mapped identifiers.
+    pub fn with_identifier_map(mut self, map: Vec<(String, String)>) -> Self {
+        self.identifier_map = map;
+        // Sort by descending prefix length, so that the first match is the longest
+        self.identifier_map
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
     }
 
-    /// Flush log records.
+    /// The syslog identifier for `record` with `settings`.
+    fn syslog_identifier<'a>(
+        &'a self,
+        settings: &'a config::Settings,
+        record: &'a Record,
+    ) -> &'a str {
+        let mapped = self
+            .identifier_map
+            .iter()
+            .find(|(prefix, _)| record.target().starts_with(prefix.as_str()));
+        if let Some((_, identifier)) = mapped {
+            identifier
+        } else if self.target_as_identifier && !self.explicit_syslog_identifier {
+            record.target()
+        } else {
+            &settings.syslog_identifier
+        }
+    }
+
+    /// Set a transport tag for this logger.
     ///
-    /// A no-op for journal logging.
-    fn flush(&self) {}
+    /// The logger writes this tag in the custom `TRANSPORT` field, to tell
+    /// apart the subsystems of an application which emitted a log record.
+    /// Note that this field is distinct from the trusted `_TRANSPORT` field
+    /// which journald sets itself.
+    ///
+    /// Records can override the tag with a `transport` key-value.
+    pub fn with_transport_tag(mut self, tag: impl Into<String>) -> Self {
+        self.transport_tag = Some(tag.into());
+        self
+    }
+
+    /// Whether to add the ids of the current OpenTelemetry span to log records.
+    ///
+    /// If enabled the logger adds the trace and span id of the span in the
+    /// current OpenTelemetry context as lowercase hex strings in the `TRACE_ID`
+    /// and `SPAN_ID` fields.  If there's no active span the logger omits both
+    /// fields.
+    ///
+    /// Disabled by default.
+    #[cfg(feature = "otel")]
+    pub fn with_current_span_context(mut self, enabled: bool) -> Self {
+        self.current_span_context = enabled;
+        self
+    }
+
+    /// Also write log records as JSON lines to `writer`.
+    ///
+    /// In addition to sending records to the journal write a compact JSON
+    /// object with the numeric `priority`, the `message` and all key-values
+    /// in `fields`, under their escaped field names, followed by a newline,
+    /// for every record to `writer`.  Like [`Self::with_tee_stderr`] this only
+    /// applies to records logged through [`Log::log`].
+    ///
+    /// The logger ignores errors when writing to `writer`.
+    ///
+    /// Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn with_json_tee(mut self, writer: Arc<std::sync::Mutex<dyn Write + Send>>) -> Self {
+        self.json_tee = Some(writer);
+        self
+    }
+
+    /// Whether to also write log records to standard error.
+    ///
+    /// If enabled the logger writes a concise line with the level, the target
+    /// and the message of every log record to standard error, in addition to
+    /// sending the record to the journal.  If standard error is a terminal the
+    /// logger highlights the level with colors.
+    ///
+    /// This is useful for interactive processes whose output is also
+    /// captured by the journal.
+    ///
+    /// Disabled by default.
+    pub fn with_tee_stderr(mut self, enabled: bool) -> Self {
+        self.tee_stderr = enabled;
+        self
+    }
+
+    /// Whether to also write records to the kernel log.
+    ///
+    /// If enabled the logger additionally writes a line with the priority,
+    /// syslog identifier and message of every record to `/dev/kmsg`, for
+    /// early boot and initramfs tools whose logs should also end up in the
+    /// kernel ring buffer.  This requires privileges; the logger reports
+    /// failed writes to the error hook (see [`Self::on_error`]), and otherwise
+    /// ignores them.
+    ///
+    /// Disabled by default.
+    pub fn with_kmsg_tee(mut self, enabled: bool) -> Self {
+        self.kmsg_tee = enabled;
+        self
+    }
+
+    /// Set what to do with records whose message is empty.
+    ///
+    /// By default the logger sends such records with an empty `MESSAGE`
+    /// (see [`EmptyMessagePolicy::Send`]); use [`EmptyMessagePolicy::Skip`] to
+    /// discard these records, or [`EmptyMessagePolicy::Placeholder`] to send
+    /// them with a placeholder text instead.
+    pub fn with_empty_message_policy(mut self, policy: EmptyMessagePolicy) -> Self {
+        self.empty_message_policy = policy;
+        self
+    }
+
+    /// Only send every `keep_every`th record at or below `level`.
+    ///
+    /// For records with `level` or a more verbose level the logger counts
+    /// records per target, and only sends the first and then every
+    /// `keep_every`th record of each target.  It adds `SAMPLED=1` and
+    /// `SAMPLE_RATE=keep_every` to these records, so consumers can
+    /// extrapolate.  Discarded records count as suppressed (see
+    /// [`Self::suppressed_count`]).
+    ///
+    /// A `keep_every` of 0 or 1 keeps all records and adds no fields.  By
+    /// default the logger doesn't sample records.
+    pub fn with_sampling(mut self, level: Level, keep_every: u32) -> Self {
+        self.sampler = Some(sampling::Sampler::new(level, keep_every));
+        self
+    }
+
+    /// Whether to discard `record` according to the empty message policy or sampling.
+    ///
+    /// Count discarded records as suppressed.
+    fn skips(&self, record: &Record) -> bool {
+        let skip = (self.empty_message_policy == EmptyMessagePolicy::Skip
+            && empty_message::is_empty_message(record))
+            || self
+                .sampler
+                .as_ref()
+                .is_some_and(|sampler| !sampler.keeps(record));
+        if skip {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+        skip
+    }
+
+    /// Get the number of records this logger suppressed so far.
+    ///
+    /// Count all records which the logger discarded on purpose instead of
+    /// sending them, e.g. because of the empty message policy (see
+    /// [`Self::with_empty_message_policy`]) or sampling (see
+    /// [`Self::with_sampling`]).  Records which the logger failed
+    /// to send count as dropped instead (see [`Self::diagnostics`]).
+    pub fn suppressed_count(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+
+    /// Map log levels to priorities with `mapping`.
+    ///
+    /// The logger calls `mapping` with the level of every record, and writes
+    /// the returned priority in the `PRIORITY` field, instead of the default
+    /// mapping documented for [`JournalLog`] (see [`Priority::from`]).  A
+    /// `severity` key-value on the record still overrides the mapped priority.
+    pub fn with_priority_mapping(
+        mut self,
+        mapping: impl Fn(Level) -> Priority + Send + Sync + 'static,
+    ) -> Self {
+        self.priority_mapping = Some(Box::new(mapping));
+        self
+    }
+
+    /// Never write a less severe priority than `floor`.
+    ///
+    /// Clamp the priority of every record to at most `floor`, e.g. with a
+    /// floor of `5` the logger writes debug and trace records with priority
+    /// `5` (notice).  This reduces the number of distinct priorities for
+    /// consumers which only index severe records.  The floor applies to the
+    /// final priority, i.e. after the priority mapping (see
+    /// [`Self::with_priority_mapping`]) and a `severity` key-value.
+    ///
+    /// A `floor` greater than `7` (debug) has no effect.
+    pub fn with_priority_floor(mut self, floor: u8) -> Self {
+        self.priority_floor = Priority::from_u8(floor);
+        self
+    }
+
+    /// Never write a more severe priority than a ceiling for targets with a given prefix.
+    ///
+    /// `map` maps target prefixes to ceilings.  Clamp the priority of records
+    /// whose target starts with a prefix to at least its ceiling, e.g. with
+    /// `("chatty".to_string(), 4)` the logger writes error records of target
+    /// `chatty::db` with priority `4` (warning), so they never trigger alerts
+    /// on errors.  If several prefixes match, the longest one wins.  Like the
+    /// floor (see [`Self::with_priority_floor`]) the ceiling applies to the
+    /// final priority; the floor applies after the ceiling.
+    ///
+    /// The logger ignores ceilings greater than `7` (debug).  Remove all
+    /// previously set ceilings.
+    pub fn with_target_priority_ceiling(mut self, map: Vec<(String, u8)>) -> Self {
+        self.target_priority_ceilings = map
+            .into_iter()
+            .filter_map(|(prefix, ceiling)| Some((prefix, Priority::from_u8(ceiling)?)))
+            .collect();
+        // Sort by descending prefix length, so that the first match is the longest
+        self.target_priority_ceilings
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Get the priority of `record`.
+    ///
+    /// Use the priority of a `severity` key-value if any, and map the level of
+    /// `record` otherwise.
+    fn record_priority(&self, record: &Record) -> Priority {
+        let severity = record.key_values().get(Key::from_str("severity"));
+        let priority = match severity
+            .and_then(|severity| Priority::from_severity_name(&severity.to_string()))
+        {
+            Some(priority) => priority,
+            None => match &self.priority_mapping {
+                Some(mapping) => mapping(record.level()),
+                None => Priority::from(record.level()),
+            },
+        };
+        let ceiling = self
+            .target_priority_ceilings
+            .iter()
+            .find(|(prefix, _)| record.target().starts_with(prefix.as_str()));
+        let priority = match ceiling {
+            Some((_, ceiling)) => priority.max(*ceiling),
+            None => priority,
+        };
+        match self.priority_floor {
+            Some(floor) => priority.min(floor),
+            None => priority,
+        }
+    }
+
+    /// Get the standard fields this logger writes for `record`.
+    ///
+    /// Return the names and values of all standard fields (see [`JournalLog`])
+    /// in the order the logger writes them, honouring the selected standard
+    /// fields, the maximum message length and the empty message policy.  The
+    /// values are not encoded for the journal protocol.
+    ///
+    /// This is useful to build journal entries for other transports.
+    pub fn standard_fields(&self, record: &Record) -> Vec<(String, Vec<u8>)> {
+        let settings = self.settings();
+        self.standard_fields_with(&settings, record)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into_owned()))
+            .collect()
+    }
+
+    /// Get the standard fields for `record` with `settings`.
+    fn standard_fields_with<'a>(
+        &'a self,
+        settings: &'a config::Settings,
+        record: &'a Record,
+    ) -> Vec<(&'a str, Cow<'a, [u8]>)> {
+        let fields = match self.lean_below {
+            Some(level) if level < record.level() => {
+                settings.standard_fields
+                    & (StandardFields::PRIORITY | StandardFields::MESSAGE | StandardFields::TARGET)
+            }
+            _ => settings.standard_fields,
+        };
+        let mut pairs = Vec::with_capacity(8);
+        if fields.contains(StandardFields::PRIORITY) {
+            let priority = self.record_priority(record).as_field_value();
+            pairs.push(("PRIORITY", Cow::Borrowed(priority)));
+        }
+        if fields.contains(StandardFields::MESSAGE) {
+            let message = match &self.empty_message_policy {
+                EmptyMessagePolicy::Placeholder(text)
+                    if empty_message::is_empty_message(record) =>
+                {
+                    Cow::Borrowed(text.as_str())
+                }
+                _ => match record.args().as_str() {
+                    Some(message) => Cow::Borrowed(message),
+                    None => Cow::Owned(record.args().to_string()),
+                },
+            };
+            let message = match &self.message_transform {
+                Some(transform) => Cow::Owned(transform(&message)),
+                None => message,
+            };
+            let message = if self.append_kv_to_message && record.key_values().count() != 0 {
+                let mut message = if self.trim_trailing_newline {
+                    trim_trailing_newline(&message).to_string()
+                } else {
+                    message.into_owned()
+                };
+                // Formatting into a string doesn't fail
+                let _ = record
+                    .key_values()
+                    .visit(&mut AppendKeyValues(&mut message));
+                Cow::Owned(message)
+            } else {
+                message
+            };
+            let message = match message {
+                Cow::Borrowed(message) => Cow::Borrowed(self.shorten_message(message)),
+                Cow::Owned(message) => {
+                    let len = self.shorten_message(&message).len();
+                    let mut message = message;
+                    message.truncate(len);
+                    Cow::Owned(message)
+                }
+            };
+            let message = match message {
+                Cow::Borrowed(message) => Cow::Borrowed(message.as_bytes()),
+                Cow::Owned(message) => Cow::Owned(message.into_bytes()),
+            };
+            pairs.push(("MESSAGE", message));
+        }
+        // Syslog compatibility fields
+        let syslog_identifier = self.syslog_identifier(settings, record);
+        if fields.contains(StandardFields::SYSLOG_IDENTIFIER) && !syslog_identifier.is_empty() {
+            pairs.push((
+                "SYSLOG_IDENTIFIER",
+                Cow::Borrowed(syslog_identifier.as_bytes()),
+            ));
+        }
+        if fields.contains(StandardFields::SYSLOG_PID) {
+            let pid = std::process::id().to_string().into_bytes();
+            pairs.push(("SYSLOG_PID", Cow::Owned(pid)));
+        }
+        if let Some(file) = record
+            .file()
+            .filter(|_| fields.contains(StandardFields::CODE_FILE))
+        {
+            pairs.push(("CODE_FILE", Cow::Borrowed(file.as_bytes())));
+        }
+        if let Some(line) = record
+            .line()
+            .filter(|_| fields.contains(StandardFields::CODE_LINE))
+        {
+            pairs.push(("CODE_LINE", Cow::Owned(line.to_string().into_bytes())));
+        }
+        if let Some(module) = record
+            .module_path()
+            .filter(|_| fields.contains(StandardFields::CODE_MODULE))
+            .and_then(|module| self.module_path(module))
+        {
+            pairs.push(("CODE_MODULE", Cow::Borrowed(module.as_bytes())));
+        }
+        if fields.contains(StandardFields::TARGET) {
+            pairs.push((
+                &settings.target_field_name,
+                Cow::Borrowed(record.target().as_bytes()),
+            ));
+        }
+        pairs
+    }
+
+    /// The module path to write in `CODE_MODULE` for `module`, if any.
+    fn module_path<'m>(&self, module: &'m str) -> Option<&'m str> {
+        if self.module_strip_crate {
+            module.split_once("::").map(|(_, module)| module)
+        } else {
+            Some(module)
+        }
+    }
+
+    /// Trim and truncate `message` as configured.
+    fn shorten_message<'m>(&self, message: &'m str) -> &'m str {
+        let message = if self.trim_trailing_newline {
+            trim_trailing_newline(message)
+        } else {
+            message
+        };
+        truncate_at_char_boundary(message, self.max_message_len.unwrap_or(usize::MAX))
+    }
+
+    /// Put all standard fields for `record` with `settings` into `buffer`.
+    ///
+    /// Return the length of `MESSAGE` in bytes, if written.
+    fn put_standard_fields(
+        &self,
+        settings: &config::Settings,
+        buffer: &mut Vec<u8>,
+        record: &Record,
+    ) -> Option<usize> {
+        fields::put_standard_fields(buffer, self.standard_fields_with(settings, record))
+    }
+
+    /// Encode `record` in the native journal protocol.
+    ///
+    /// Return the complete journal payload for `record`, with all standard
+    /// and custom fields of the record and all extra fields of this logger,
+    /// in the order documented for [`JournalLog`].
+    ///
+    /// Don't check for colliding keys; with [`CollisionPolicy::Error`] this
+    /// encodes records which [`Self::journal_send`] rejects (see
+    /// [`Self::with_collision_policy`]).
+    pub fn journal_encode(&self, record: &Record) -> Vec<u8> {
+        self.encode_record(record, &[], EncodeMode::Encode).payload
+    }
+
+    /// Estimate the size of the journal payload for `record`.
+    ///
+    /// Return the number of bytes [`Self::journal_encode`] would return for
+    /// `record`, including all standard fields and extra fields, without
+    /// sending anything.  This helps to decide about budgets and backpressure
+    /// before logging a record.  Like [`Self::journal_encode`] this doesn't
+    /// check for colliding keys.
+    ///
+    /// Estimating has no side effects: it doesn't advance the sequence number
+    /// (see [`Self::with_sequence_field`]), doesn't assign a thread id (see
+    /// [`Self::with_thread_id_field`]), and doesn't call the error hook (see
+    /// [`Self::on_error`]).  The estimate is exact, unless fields
+    /// of variable length change between estimating and encoding, e.g. the
+    /// thread CPU time or the current time of a clock.
+    pub fn estimate_size(&self, record: &Record) -> usize {
+        self.encode_record(record, &[], EncodeMode::Estimate)
+            .payload
+            .len()
+    }
+
+    /// Encode `record` with timestamp fields from the clock, if any.
+    ///
+    /// See [`EncodeMode`] for `mode`.
+    fn encode_record(
+        &self,
+        record: &Record,
+        logger_fields: LoggerFields,
+        mode: EncodeMode,
+    ) -> Encoded {
+        let mut encoded = self.encode_fields_with(record, logger_fields, mode);
+        if let Some(clock) = &self.clock {
+            // Omit timestamp fields for times before the epoch
+            let _ = timestamp::put_timestamp_fields(&mut encoded.payload, clock());
+        }
+        self.finish_payload(encoded)
+    }
+
+    /// Redact fields of the `encoded` payload and mark it if it's large.
+    fn finish_payload(&self, encoded: Encoded) -> Encoded {
+        let mut payload = match &self.field_redactor {
+            Some(redactor) => redact::redact(&encoded.payload, redactor),
+            None => encoded.payload,
+        };
+        self.put_size_warning(&mut payload);
+        Encoded { payload, ..encoded }
+    }
+
+    /// Put size warning fields into `payload` if it exceeds the threshold.
+    fn put_size_warning(&self, payload: &mut Vec<u8>) {
+        if let Some(threshold) = self.size_warning_threshold {
+            let size = payload.len();
+            if threshold < size {
+                writeln!(payload, "ENTRY_SIZE={}", size).unwrap();
+                payload.extend_from_slice(b"ENTRY_SIZE_WARN=1\n");
+            }
+        }
+    }
+
+    /// Encode all fields of `record`, without timestamp fields.
+    fn encode_fields(&self, record: &Record) -> Encoded {
+        self.encode_fields_with(record, &[], EncodeMode::Encode)
+    }
+
+    /// Encode all fields of `record` in `mode`, without timestamp fields.
+    ///
+    /// Put `logger_fields` right after the standard fields.
+    ///
+    /// Count key-values which fail to serialize, but don't report them; the
+    /// caller reports them when sending the payload.
+    fn encode_fields_with(
+        &self,
+        record: &Record,
+        logger_fields: LoggerFields,
+        mode: EncodeMode,
+    ) -> Encoded {
+        // Use the same settings for the entire record
+        let settings = self.settings();
+        let mut payload = Vec::with_capacity(1024);
+        let key_values = SortedKeyValues::of(
+            record,
+            self.max_fields.unwrap_or(usize::MAX),
+            self.collision_policy,
+            self.unicode_field_policy,
+            &self.passthrough_fields,
+        );
+        let message_len = self.put_standard_fields(&settings, &mut payload, record);
+        for (name, value) in logger_fields {
+            put_field_bytes(&mut payload, FieldName::WellFormed(name), value);
+        }
+        if let Some(tag) = &self.transport_tag {
+            // A transport key-value on the record overrides the tag
+            if !key_values.contains(b"TRANSPORT") {
+                put_field_bytes(
+                    &mut payload,
+                    FieldName::WellFormed("TRANSPORT"),
+                    tag.as_bytes(),
+                );
+            }
+        }
+        if let Some(fallback) = &self.comm_fallback {
+            let thread = std::thread::current();
+            let comm = thread.name().unwrap_or(fallback);
+            put_field_bytes(&mut payload, FieldName::WellFormed("COMM"), comm.as_bytes());
+        }
+        if self.thread_id_field {
+            writeln!(payload, "RUST_THREAD_ID={}", current_thread_id(mode)).unwrap();
+        }
+        #[cfg(feature = "async")]
+        if let Some(task_id) = tokio::task::try_id().filter(|_| self.task_id_field) {
+            writeln!(payload, "TASK_ID={}", task_id).unwrap();
+        }
+        if self.cpu_time_field {
+            if let Some(cpu_time) = thread_cpu_time() {
+                writeln!(payload, "THREAD_CPU_US={}", cpu_time.as_micros()).unwrap();
+            }
+        }
+        if self.fd_count_field {
+            if let Some(count) = open_fd_count() {
+                writeln!(payload, "OPEN_FDS={}", count).unwrap();
+            }
+        }
+        if self.kv_count_field {
+            writeln!(payload, "KV_COUNT={}", key_values.total).unwrap();
+        }
+        if let Some(len) = message_len.filter(|_| self.message_len_field) {
+            writeln!(payload, "MESSAGE_LEN={}", len).unwrap();
+        }
+        if self.sequence_field {
+            let sequence = match mode {
+                EncodeMode::Encode => self.sequence.fetch_add(1, Ordering::Relaxed),
+                EncodeMode::Estimate => self.sequence.load(Ordering::Relaxed),
+            };
+            writeln!(payload, "SEQ={}", sequence).unwrap();
+        }
+        payload.extend_from_slice(&self.credential_fields);
+        payload.extend_from_slice(&self.machine_id_field);
+        payload.extend_from_slice(&self.exe_path_field);
+        if let Some(commit) = &self.git_commit {
+            put_field_bytes(
+                &mut payload,
+                FieldName::WellFormed("GIT_COMMIT"),
+                commit.as_bytes(),
+            );
+        }
+        if self.build_profile_field {
+            let profile = if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            };
+            writeln!(payload, "BUILD_PROFILE={}", profile).unwrap();
+        }
+        for (field_name, env_var) in &self.env_fields {
+            if let Some(value) = std::env::var_os(env_var) {
+                put_field_bytes(
+                    &mut payload,
+                    FieldName::WriteEscaped(field_name),
+                    value.as_bytes(),
+                );
+            }
+        }
+        if let Some(sampler) = self
+            .sampler
+            .as_ref()
+            .filter(|sampler| sampler.samples(record.level()))
+        {
+            payload.extend_from_slice(b"SAMPLED=1\n");
+            writeln!(payload, "SAMPLE_RATE={}", sampler.keep_every()).unwrap();
+        }
+        #[cfg(feature = "otel")]
+        if self.current_span_context {
+            otel::put_current_span_context(&mut payload);
+        }
+        let merged = match &self.field_merge_separator {
+            Some(separator) => {
+                self.put_merged_extra_fields(&settings, &mut payload, &key_values, separator)
+            }
+            None => {
+                payload.extend_from_slice(&settings.extra_fields);
+                Vec::new()
+            }
+        };
+        scoped_fields::put_scoped_fields(&mut payload);
+        for (name, field) in &self.default_fields {
+            if !key_values.contains(name) {
+                payload.extend_from_slice(field);
+            }
+        }
+        let dropped = key_values.dropped;
+        let visit_errors = usize::from(key_values.failed);
+        let put = self.put_key_values(&mut payload, key_values, &merged);
+        if 0 < dropped {
+            writeln!(payload, "FIELDS_DROPPED={}", dropped).unwrap();
+        }
+        if put.budget_exceeded {
+            payload.extend_from_slice(b"BUDGET_EXCEEDED=1\n");
+        }
+        let errors = visit_errors + put.errors;
+        if 0 < errors {
+            writeln!(payload, "KV_ERRORS={}", errors).unwrap();
+        }
+        Encoded {
+            payload,
+            kv_errors: errors,
+        }
+    }
+
+    /// Put extra fields of `settings` into `buffer`, merged with `key_values` of the same name.
+    ///
+    /// Return the names of all merged fields.
+    fn put_merged_extra_fields<'a>(
+        &self,
+        settings: &'a config::Settings,
+        buffer: &mut Vec<u8>,
+        key_values: &SortedKeyValues,
+        separator: &str,
+    ) -> Vec<&'a [u8]> {
+        let mut merged: Vec<(&[u8], Vec<u8>)> = Vec::new();
+        for (name, value) in &settings.extra_field_values {
+            if !key_values.contains(name) {
+                put_field_bytes(buffer, FieldName::Escaped(name), value);
+            } else if let Some((_, merged_value)) = merged.iter_mut().find(|(n, _)| n == name) {
+                merged_value.extend_from_slice(separator.as_bytes());
+                merged_value.extend_from_slice(value);
+            } else {
+                merged.push((name, value.clone()));
+            }
+        }
+        for (name, merged_value) in &mut merged {
+            for (_, value) in key_values.key_values.iter().filter(|(n, _)| n == name) {
+                merged_value.extend_from_slice(separator.as_bytes());
+                write!(merged_value, "{}", value).unwrap();
+            }
+            put_field_bytes(buffer, FieldName::Escaped(name), merged_value);
+        }
+        merged.into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Put all `key_values` of a record into `buffer`, except for `merged` names.
+    ///
+    /// Stop at the first key-value which would make `buffer` exceed the entry
+    /// budget, and skip key-values which fail to format.
+    fn put_key_values(
+        &self,
+        buffer: &mut Vec<u8>,
+        key_values: SortedKeyValues,
+        merged: &[&[u8]],
+    ) -> PutKeyValues {
+        let mut put = PutKeyValues::default();
+        let budget = self.max_entry_bytes.unwrap_or(usize::MAX);
+        for (name, value) in key_values.key_values {
+            if merged.contains(&name.as_slice())
+                || (self.empty_field_policy == EmptyFieldPolicy::Drop
+                    && empty_field::is_empty_value(&value))
+            {
+                continue;
+            }
+            let start = buffer.len();
+            match (value.to_bool(), value.to_borrowed_error()) {
+                (Some(value), _) if self.numeric_booleans => {
+                    let value: &[u8] = if value { b"1" } else { b"0" };
+                    put_field_bytes(buffer, FieldName::Escaped(&name), value);
+                }
+                (_, Some(error)) => {
+                    // Write errors with their debug representation as well
+                    let debug_name = suffix_field_name(&name, b"_DEBUG");
+                    let written =
+                        try_put_field_length_encoded(buffer, FieldName::Escaped(&name), &value)
+                            .and_then(|_| {
+                                try_put_field_length_encoded(
+                                    buffer,
+                                    FieldName::Escaped(&debug_name),
+                                    &Value::from_debug(&error),
+                                )
+                            });
+                    if written.is_err() {
+                        buffer.truncate(start);
+                        put.errors += 1;
+                        continue;
+                    }
+                }
+                _ => {
+                    if try_put_field_length_encoded(buffer, FieldName::Escaped(&name), &value)
+                        .is_err()
+                    {
+                        put.errors += 1;
+                        continue;
+                    }
+                }
+            }
+            if budget < buffer.len() {
+                buffer.truncate(start);
+                put.budget_exceeded = true;
+                break;
+            }
+        }
+        put
+    }
+
+    /// Set what to do with key-values whose value is empty.
+    ///
+    /// Some consumers treat empty fields such as `NOTE=` like missing fields;
+    /// with [`EmptyFieldPolicy::Drop`] the logger omits key-values of records
+    /// whose value formats to an empty string.  The policy doesn't apply to
+    /// standard fields, and extra and default fields of the logger.
+    ///
+    /// Defaults to [`EmptyFieldPolicy::Keep`].
+    pub fn with_empty_field_policy(mut self, policy: EmptyFieldPolicy) -> Self {
+        self.empty_field_policy = policy;
+        self
+    }
+
+    /// Set what to do with non-ASCII characters in keys of key-values.
+    ///
+    /// By default the logger replaces non-ASCII characters with underscores
+    /// like all other invalid characters, so `Hallöchen` becomes `HALL_CHEN`.
+    /// With [`UnicodeFieldPolicy::Transliterate`] the logger instead folds
+    /// common Latin letters to ASCII before escaping, so `Hallöchen` becomes
+    /// `HALLOECHEN`.  The policy doesn't apply to extra and default fields of
+    /// the logger.
+    ///
+    /// Defaults to [`UnicodeFieldPolicy::Replace`].
+    pub fn with_unicode_field_policy(mut self, policy: UnicodeFieldPolicy) -> Self {
+        self.unicode_field_policy = policy;
+        self
+    }
+
+    /// Use the keys `names` as field names without escaping them.
+    ///
+    /// The logger writes key-values with these keys verbatim, and skips
+    /// escaping for them, which saves some work for a fixed set of custom
+    /// fields which applications log over and over again.  The logger still
+    /// escapes all other keys.  Remove all previously set names.
+    ///
+    /// # Errors
+    ///
+    /// Fail with [`Error::InvalidFieldName`] if any of `names` is not a valid
+    /// journal field name (see [`Self::with_logger_field_name`]).
+    pub fn with_passthrough_fields(mut self, names: HashSet<String>) -> Result<Self, Error> {
+        if let Some(name) = names.iter().find(|name| !is_valid_field_name(name)) {
+            return Err(Error::InvalidFieldName(name.clone()));
+        }
+        self.passthrough_fields = names;
+        Ok(self)
+    }
+
+    /// Rewrite messages with `f` before writing them as `MESSAGE`.
+    ///
+    /// The logger calls `f` with the formatted message of every record, or
+    /// the placeholder for empty messages (see
+    /// [`Self::with_empty_message_policy`]), and writes the returned string
+    /// instead, e.g. to prefix messages with a tag or to strip ANSI escape
+    /// sequences.  The logger trims and truncates the returned string (see
+    /// [`Self::with_trim_trailing_newline`] and [`Self::with_max_message_len`]).
+    /// It decides whether a message is empty before calling `f`.
+    pub fn with_message_transform(
+        mut self,
+        f: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.message_transform = Some(Box::new(f));
+        self
+    }
+
+    /// Limit the length of the `MESSAGE` field.
+    ///
+    /// Truncate messages longer than `max_len` bytes.  The logger never splits
+    /// a UTF-8 code point, so truncated messages may be slightly shorter than
+    /// `max_len` bytes.
+    ///
+    /// By default the logger doesn't limit the message length.
+    pub fn with_max_message_len(mut self, max_len: usize) -> Self {
+        self.max_message_len = Some(max_len);
+        self
+    }
+
+    /// Whether to remove a trailing newline from `MESSAGE`.
+    ///
+    /// If enabled the logger removes exactly one trailing newline, and a
+    /// carriage return right before it, from the message of every record.
+    /// This helps with messages forwarded from sources which terminate every
+    /// message with a newline.
+    ///
+    /// Disabled by default, i.e. the logger preserves messages as they are.
+    pub fn with_trim_trailing_newline(mut self, enabled: bool) -> Self {
+        self.trim_trailing_newline = enabled;
+        self
+    }
+
+    /// Set what to do with distinct keys which escape to the same field name.
+    ///
+    /// The logger canonicalizes keys of key-values to field names (see
+    /// [`JournalLog`]), so distinct keys such as `Foo` and `foo` may both
+    /// escape to the same field `FOO`.  By default the logger writes the
+    /// values of all these keys as multiple values of the same field (see
+    /// [`CollisionPolicy::Keep`]).
+    ///
+    /// With [`CollisionPolicy::Error`] [`Self::journal_send`] and
+    /// [`Self::log_at`] fail with [`Error::FieldCollision`] for records with
+    /// colliding keys, and do not send these records.
+    /// [`Self::journal_encode`] and [`Self::estimate_size`] can't fail, so they
+    /// don't check for collisions, and behave like [`CollisionPolicy::Keep`].
+    pub fn with_collision_policy(mut self, policy: CollisionPolicy) -> Self {
+        self.collision_policy = policy;
+        self
+    }
+
+    /// Fail if `record` has colliding keys and the collision policy says so.
+    fn check_collisions(&self, record: &Record) -> Result<(), Error> {
+        if self.collision_policy != CollisionPolicy::Error {
+            return Ok(());
+        }
+        let collect = CollectKeyValues::of(
+            record,
+            self.max_fields.unwrap_or(usize::MAX),
+            self.unicode_field_policy,
+            &self.passthrough_fields,
+        );
+        let keys = collect.keys();
+        match collision::find_collision(&keys, &collect.key_values) {
+            Some((i, j)) => Err(Error::FieldCollision {
+                first: keys[i].to_string(),
+                second: keys[j].to_string(),
+                field: String::from_utf8_lossy(&collect.key_values[i].0).into_owned(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Redact fields with `f` before sending them to the journal.
+    ///
+    /// The logger calls `f` with the name and value of every field of an
+    /// entry, including standard fields like `MESSAGE`, just before sending
+    /// the entry.  If `f` returns `None` the logger drops the field;
+    /// otherwise it replaces the value of the field with the returned bytes,
+    /// e.g. to mask a token.  The logger doesn't pass the size warning fields
+    /// (see [`Self::with_size_warning_field`]) to `f`, and computes the size
+    /// of the redacted entry.  `f` also sees the fields of panic entries, if
+    /// the panic hook is installed afterwards (see [`Self::install_panic_hook`]),
+    /// e.g. to redact secrets in panic messages or backtraces.
+    ///
+    /// `f` only applies to the journal; the logger doesn't redact records it
+    /// writes to standard error (see [`Self::with_tee_stderr`]) or to the
+    /// syslog fallback (see [`Self::with_syslog_fallback`]).
+    pub fn with_field_redactor(
+        mut self,
+        f: impl Fn(&str, &[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.field_redactor = Some(Arc::new(f));
+        self
+    }
+
+    /// Mark entries larger than `threshold` bytes.
+    ///
+    /// If the encoded entry of a record exceeds `threshold` bytes, the logger
+    /// adds the size of the entry in bytes as `ENTRY_SIZE`, and `ENTRY_SIZE_WARN=1`.
+    /// journald may truncate or drop large entries without the application
+    /// noticing; these fields help consumers to spot risky entries.  This is
+    /// a best-effort warning: the logger can't know the actual limits of
+    /// journald.
+    ///
+    /// By default the logger doesn't mark large entries.
+    pub fn with_size_warning_field(mut self, threshold: usize) -> Self {
+        self.size_warning_threshold = Some(threshold);
+        self
+    }
+
+    /// Limit the size of the fields of an entry to `bytes`.
+    ///
+    /// The logger tracks the size of an entry while encoding it, and stops
+    /// adding key-values of the record once the next key-value would make the
+    /// entry larger than `bytes`.  If it omits key-values it adds
+    /// `BUDGET_EXCEEDED=1` to the entry.  The logger always writes standard
+    /// fields including `MESSAGE`, all other fields of the logger, and
+    /// `FIELDS_DROPPED` (see [`Self::with_max_fields`]), even if these alone
+    /// exceed `bytes`.
+    ///
+    /// This keeps entries deterministically below journald's limits.  By
+    /// default the logger doesn't limit the size of entries.
+    pub fn with_max_entry_bytes(mut self, bytes: usize) -> Self {
+        self.max_entry_bytes = Some(bytes);
+        self
+    }
+
+    /// Limit the number of key-values per record.
+    ///
+    /// Write at most the first `max_fields` key-values of a record, to guard
+    /// against runaway key-value sets bloating entries.  If a record has more
+    /// key-values the logger drops the excess, and adds the number of dropped
+    /// key-values in the `FIELDS_DROPPED` field.  The limit doesn't apply to
+    /// standard fields, and extra and default fields of the logger.
+    ///
+    /// By default the logger doesn't limit the number of key-values.
+    pub fn with_max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = Some(max_fields);
+        self
+    }
+
+    /// Send encoded entries through `transport` instead of the journal socket.
+    ///
+    /// The logger encodes records as usual, and passes every entry in the
+    /// native journal protocol to [`JournalTransport::send`], e.g. to capture
+    /// entries in memory for tests, or to forward them to a remote journal.
+    /// Errors of `transport` go to the error hook (see [`Self::on_error`]),
+    /// and may fall back to syslog (see [`Self::with_syslog_fallback`]).
+    ///
+    /// The transport then is the only way entries leave the logger: records
+    /// which the namespace router sends to a namespace (see
+    /// [`Self::with_namespace_router`]) go to
+    /// [`JournalTransport::send_to_namespace`], panics go to `transport` if
+    /// the panic hook is installed afterwards (see
+    /// [`Self::install_panic_hook`]), and [`Self::diagnostics`] counts entries
+    /// sent and dropped by `transport`.  Socket options, e.g.
+    /// [`Self::with_socket_path`], have no effect on `transport`.
+    pub fn with_transport(mut self, transport: impl JournalTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Send log records to the journal socket at `path`.
+    ///
+    /// By default the logger sends to `/run/systemd/journal/socket`.  Unlike
+    /// the constructors this method does not check whether journald listens
+    /// at `path`.
+    ///
+    /// If `path` starts with a NUL byte, e.g. `"\0journal"`, the logger sends
+    /// to the rest of `path` in the abstract namespace, for systems which
+    /// expose the journal socket there instead of in the file system.
+    pub fn with_socket_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.client.set_path(path.into());
+        self
+    }
+
+    /// Bind the socket of this logger to `path`.
+    ///
+    /// Some security policies only allow clients bound to a specific address
+    /// to send to the journal.  Bind the datagram socket of the logger to
+    /// `path`, which must not exist yet.  If `path` starts with a NUL byte,
+    /// e.g. `"\0my-app"`, the logger binds to the rest of `path` in the
+    /// abstract namespace, which doesn't create a file.
+    ///
+    /// Sockets created later, e.g. after [`Self::with_max_level`] closed the
+    /// socket, also bind to `path`, but seqpacket sockets are never bound.
+    /// If binding a later socket fails, e.g. because another process bound
+    /// `path` in the meantime, sending fails with the error of binding.
+    ///
+    /// The logger removes the socket file at `path` when it closes its socket,
+    /// and when it's dropped.  It can't remove the file if the process
+    /// crashes though, so with a fixed `path` remove a stale socket file
+    /// before starting the logger.
+    ///
+    /// # Errors
+    ///
+    /// Fail if binding to `path` fails, e.g. because `path` is already in use.
+    pub fn with_bind_address(mut self, path: PathBuf) -> Result<Self, Error> {
+        self.client.set_bind_address(path)?;
+        Ok(self)
+    }
+
+    /// Send log records through a socket of the given `socket_type`.
+    ///
+    /// With [`SocketType::SeqPacket`] the logger connects a `SOCK_SEQPACKET`
+    /// socket to the journal socket, which preserves message boundaries more
+    /// robustly for large entries.  If connecting fails, e.g. because journald
+    /// listens on a datagram socket, the logger falls back to a datagram
+    /// socket; see [`JournalDiagnostics::socket_type`] for the socket type in
+    /// use.
+    ///
+    /// By default the logger uses a datagram socket.
+    pub fn with_socket_type(mut self, socket_type: SocketType) -> Self {
+        self.client.set_socket_type(socket_type);
+        self
+    }
+
+    /// Retry transient failures when sending records.
+    ///
+    /// If sending a record fails with `EAGAIN`, e.g. because the journal
+    /// socket is full, retry up to `attempts` times, and wait for `backoff`
+    /// before each retry.  The logger gives up afterwards, and drops the record
+    /// (see [`JournalLog`]).  The logger always retries sends interrupted by a
+    /// signal (`EINTR`), regardless of this setting.
+    ///
+    /// By default the logger doesn't retry.
+    pub fn with_retry(mut self, attempts: usize, backoff: Duration) -> Self {
+        self.client
+            .set_retry(retry::RetryPolicy { attempts, backoff });
+        self
+    }
+
+    /// Send records to the journal namespaces selected by `router`.
+    ///
+    /// The logger calls `router` with the level of every record it sends.  If
+    /// `router` returns the name of a namespace the logger sends the record to
+    /// the journal of that namespace, i.e. to `/run/systemd/journal.NAME/socket`,
+    /// and otherwise to the default journal.  For a custom socket path (see
+    /// [`Self::with_socket_path`]) the logger looks for namespace sockets in a
+    /// sibling directory with the namespace appended in the same way.
+    ///
+    /// The logger opens a socket for each namespace on first use, with the
+    /// same configuration as its default socket, and keeps it open afterwards.
+    /// Namespaces need to be configured in journald, see
+    /// `systemd-journald.service(8)`.
+    ///
+    /// If `router` returns an invalid namespace name, e.g. one containing a
+    /// `/`, the logger discards the record, and [`Self::journal_send`] fails
+    /// with [`Error::InvalidNamespace`].
+    ///
+    /// [`Self::diagnostics`] only counts records sent to the default journal,
+    /// unless the logger has a custom transport (see [`Self::with_transport`]).
+    pub fn with_namespace_router(
+        mut self,
+        router: impl Fn(Level) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.namespace_router = Some(namespace::NamespaceRouter::new(Box::new(router)));
+        self
+    }
+
+    /// Time out sending a record after `timeout`.
+    ///
+    /// Set `SO_SNDTIMEO` on the journal socket, so that a send to a slow or
+    /// stuck journal fails with `EAGAIN` after `timeout` instead of blocking
+    /// indefinitely.  The logger retries a timed out send according to
+    /// [`Self::with_retry`], and drops the record afterwards.
+    ///
+    /// A zero `timeout` disables the timeout.  By default sends never time
+    /// out.
+    pub fn with_send_timeout(mut self, timeout: Duration) -> Self {
+        let timeout = Some(timeout).filter(|timeout| !timeout.is_zero());
+        // Setting a positive timeout on a valid socket doesn't fail
+        let _ = self.client.set_send_timeout(timeout);
+        self
+    }
+
+    /// Whether to enable `SO_PASSCRED` on the journal socket.
+    ///
+    /// journald derives trusted fields such as `_PID`, `_UID` and `_GID`
+    /// from the credentials the kernel attaches to each datagram.  The logger
+    /// enables `SO_PASSCRED` on its socket by default, to state explicitly
+    /// that it wants the kernel to pass its credentials along.
+    ///
+    /// journald itself enables `SO_PASSCRED` on its receiving socket, so
+    /// disabling it here normally doesn't remove trusted fields; this option
+    /// mostly exists for testing against other receivers.
+    pub fn with_passcred(mut self, enabled: bool) -> Self {
+        // Setting a boolean socket option on a valid socket doesn't fail
+        let _ = self.client.set_passcred(enabled);
+        self
+    }
+
+    /// Whether to send records to syslog if the journal is unavailable.
+    ///
+    /// If enabled and sending a record fails because the journal socket
+    /// doesn't exist or nobody listens on it, the logger instead sends the
+    /// record as classic RFC 3164 syslog message to `/dev/log` (see
+    /// [`Self::with_syslog_socket_path`]), with the user facility, the
+    /// priority of the record, the syslog identifier, the PID, and the
+    /// message.  Syslog messages have no other fields.
+    ///
+    /// The logger still calls the error hook (see [`Self::on_error`]) and
+    /// counts the record as dropped by the journal.
+    ///
+    /// Disabled by default.
+    pub fn with_syslog_fallback(mut self, enabled: bool) -> Self {
+        self.syslog_fallback = enabled;
+        self
+    }
+
+    /// Fall back to the syslog socket at `path`.
+    ///
+    /// By default the logger falls back to `/dev/log`; see
+    /// [`Self::with_syslog_fallback`].
+    pub fn with_syslog_socket_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.syslog_path = path.into();
+        self
+    }
+
+    /// Call `f` whenever sending a record to the journal fails.
+    ///
+    /// The logger calls `f` with the error of every failed send, e.g. to
+    /// increment a metric or to report the error elsewhere.  It still discards
+    /// the error afterwards (see [`JournalLog`]).  The logger also calls `f`
+    /// if writing to the kernel log fails (see [`Self::with_kmsg_tee`]), and
+    /// once per sent record if key-values of the record fail to serialize
+    /// (see `KV_ERRORS` in [`JournalLog`]), but not when only encoding records,
+    /// e.g. with [`Self::journal_encode`].
+    ///
+    /// `f` should not panic; if it does the logger catches and ignores the panic.
+    pub fn on_error(mut self, f: impl Fn(&std::io::Error) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Whether this logger sends records with the given `level` and `target`.
+    ///
+    /// Return the same result as [`Log::enabled`] for this logger, and use the
+    /// same logic as [`Log::log`], so callers can avoid building expensive
+    /// records which the logger would discard anyway.  Note that [`Log::log`]
+    /// may still discard records with an empty message (see
+    /// [`Self::with_empty_message_policy`]).
+    ///
+    /// Return `true` if `level` is enabled by the maximum level of this logger
+    /// (see [`Self::with_max_level`]), and by the level for unattached
+    /// processes (see [`Self::with_drop_below_when_unattached`]).  The logger
+    /// does not filter records by target.
+    pub fn would_log(&self, level: Level, _target: &str) -> bool {
+        level <= self.settings().max_level && self.unattached_level.map_or(true, |max| level <= max)
+    }
+
+    /// Drop records less severe than `level` if not connected to the journal.
+    ///
+    /// If the standard error of this process isn't connected to the journal
+    /// (see [`connected_to_journal`]), e.g. when running in a terminal,
+    /// [`Log::log`] discards records less severe than `level`, to avoid
+    /// flooding the terminal when the logger also writes to standard error
+    /// (see [`Self::with_tee_stderr`]).  If connected to the journal the
+    /// logger sends all records.
+    ///
+    /// The logger checks whether the process is connected to the journal
+    /// when calling this method, not for every record.  Like
+    /// [`Self::with_max_level`] this only applies to [`Log`].
+    pub fn with_drop_below_when_unattached(mut self, level: Level) -> Self {
+        self.unattached_level = (!connected_to_journal()).then_some(level);
+        self
+    }
+
+    /// Only send records up to `level` through [`Log::log`].
+    ///
+    /// With [`LevelFilter::Off`] [`Log::enabled`] always returns `false`, and
+    /// the logger closes its journal socket, to not hold on to a file
+    /// descriptor it doesn't need.  If a later call raises the level again the
+    /// logger reopens the socket on the next record it sends.
+    ///
+    /// Note that this filter only applies to [`Log`]; explicit calls to
+    /// [`Self::journal_send`] and friends send records regardless of level.
+    /// It also doesn't replace [`log::set_max_level`], which still needs to be
+    /// set to let records reach the logger at all.
+    ///
+    /// Defaults to [`LevelFilter::Trace`], i.e. the logger sends all records.
+    pub fn with_max_level(mut self, level: LevelFilter) -> Self {
+        self.settings_mut().max_level = level;
+        if level == LevelFilter::Off {
+            self.client.close();
+        }
+        self
+    }
+
+    /// Keep the most recent `capacity` entries in memory.
+    ///
+    /// The logger remembers every entry it sends, whether delivery succeeds
+    /// or not, and forgets the oldest entry once it has `capacity` entries.
+    /// Use [`Self::recent`] to get the entries, e.g. to include the last log
+    /// lines in a crash report.  A `capacity` of `0` disables the buffer.
+    ///
+    /// Disabled by default.
+    pub fn with_ring_buffer(mut self, capacity: usize) -> Self {
+        self.ring_buffer = (0 < capacity).then(|| ring::RingBuffer::new(capacity));
+        self
+    }
+
+    /// Get the most recent entries of this logger, oldest first.
+    ///
+    /// Return an empty list if the ring buffer is disabled (see
+    /// [`Self::with_ring_buffer`]).
+    pub fn recent(&self) -> Vec<EntrySnapshot> {
+        self.ring_buffer
+            .as_ref()
+            .map_or_else(Vec::new, |ring_buffer| ring_buffer.entries())
+    }
+
+    /// Get diagnostic information about this logger.
+    ///
+    /// Return information about the journal socket and the number of records
+    /// sent and dropped so far, to help debugging delivery problems.
+    ///
+    /// With the `metrics` feature the logger also counts sent and dropped
+    /// records in the counters `journal_logger_sent_total` and
+    /// `journal_logger_dropped_total` of the `metrics` facade.
+    pub fn diagnostics(&self) -> JournalDiagnostics {
+        JournalDiagnostics::from_client(&self.client)
+    }
+
+    /// Get a snapshot of the configuration of this logger.
+    ///
+    /// Return the current syslog identifier, extra fields, level filter and
+    /// a few other settings, for debugging or to show them to users.  The
+    /// snapshot doesn't change when the logger changes afterwards.
+    pub fn config(&self) -> JournalConfig {
+        let settings = self.settings();
+        JournalConfig {
+            syslog_identifier: settings.syslog_identifier.clone(),
+            extra_fields: settings
+                .extra_field_values
+                .iter()
+                .map(|(name, value)| (String::from_utf8_lossy(name).into_owned(), value.clone()))
+                .collect(),
+            max_level: settings.max_level,
+            standard_fields: settings.standard_fields,
+            target_field_name: settings.target_field_name.clone(),
+            socket_path: self.client.path().to_path_buf(),
+            socket_type: self.client.socket_type(),
+        }
+    }
+
+    /// Atomically replace the configuration of this logger with `config`.
+    ///
+    /// Replace the syslog identifier, the extra fields, the maximum level, the
+    /// standard fields and the target field name with those of `config`,
+    /// while the logger keeps logging and keeps its journal socket open.  This
+    /// suits daemons which reload their configuration on `SIGHUP`, e.g.
+    ///
+    /// ```
+    /// # use log::LevelFilter;
+    /// # use systemd_journal_logger::JournalLog;
+    /// # let logger = JournalLog::new().unwrap();
+    /// let mut config = logger.config();
+    /// config.max_level = LevelFilter::Warn;
+    /// logger.reconfigure(config);
+    /// ```
+    ///
+    /// Every record sees either the entire old or the entire new
+    /// configuration, never a mix of both.  The logger ignores the socket path
+    /// and socket type of `config`, and unlike [`Self::with_max_level`] it
+    /// doesn't close the socket for [`LevelFilter::Off`].
+    pub fn reconfigure(&self, config: JournalConfig) {
+        let settings = Arc::new(config::Settings::from_config(config));
+        *sync::write_ignoring_poison(&self.settings) = settings;
+    }
+
+    /// Validate the configuration of this logger without logging anything.
+    ///
+    /// Check that
+    ///
+    /// - the names of all extra fields, default fields, and fields read from
+    ///   the environment are valid journal field names which the logger
+    ///   needn't escape (see [`Self::add_extra_field`]),
+    /// - the socket path exists, unless it's an abstract address (see
+    ///   [`Self::with_socket_path`]), and
+    /// - the namespace router returns valid namespaces for all levels (see
+    ///   [`Self::with_namespace_router`]).
+    ///
+    /// # Errors
+    ///
+    /// Return the first problem found, i.e. [`Error::InvalidFieldName`] for
+    /// an invalid field name, [`Error::SocketUnavailable`] if the socket path
+    /// doesn't exist, or [`Error::InvalidNamespace`] for an invalid namespace.
+    pub fn validate(&self) -> Result<(), Error> {
+        let settings = self.settings();
+        let field_names = settings
+            .extra_field_names
+            .iter()
+            .chain(&self.default_field_names)
+            .chain(self.env_fields.iter().map(|(name, _)| name));
+        if let Some(name) = field_names
+            .into_iter()
+            .find(|name| !is_valid_field_name(name))
+        {
+            return Err(Error::InvalidFieldName(name.clone()));
+        }
+        // Abstract addresses have no file to check
+        if !socket::is_abstract(self.client.path()) {
+            std::fs::metadata(self.client.path()).map_err(Error::SocketUnavailable)?;
+        }
+        match &self.namespace_router {
+            Some(router) => router.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a single log record to the journal.
+    ///
+    /// Extract all fields (standard and custom) from `record` (`see [`JournalLog`]),
+    /// append all `extra_fields` given to this logger, and send the result to
+    /// journald.
+    ///
+    /// Do nothing if the empty message policy discards `record` (see
+    /// [`Self::with_empty_message_policy`]), or if sampling discards it (see
+    /// [`Self::with_sampling`]).
+    ///
+    /// Return where `record` went, i.e. to the journal, or to syslog if the
+    /// journal was unavailable and the syslog fallback is enabled (see
+    /// [`Self::with_syslog_fallback`]), or [`Delivery::Discarded`] if the
+    /// logger discarded `record`.
+    pub fn journal_send(&self, record: &Record) -> Result<Delivery, Error> {
+        if self.skips(record) {
+            return Ok(Delivery::Discarded);
+        }
+        self.check_collisions(record)?;
+        self.send_record_payload(record, &self.encode_record(record, &[], EncodeMode::Encode))
+    }
+
+    /// Send a single log record which originally occurred at `when` to the journal.
+    ///
+    /// Like [`Self::journal_send`], but also add `SYSLOG_TIMESTAMP` with `when`
+    /// as classic syslog timestamp in local time, and `SOURCE_REALTIME_TIMESTAMP`
+    /// with `when` in microseconds since the epoch.  This is useful when
+    /// replaying events which happened earlier.
+    ///
+    /// Note that journald doesn't allow clients to set the trusted
+    /// `_SOURCE_REALTIME_TIMESTAMP` field, hence the logger uses a custom
+    /// field without leading underscore.
+    ///
+    /// # Errors
+    ///
+    /// Fail if `when` is before the epoch, or if sending fails.
+    pub fn log_at(&self, when: SystemTime, record: &Record) -> Result<(), Error> {
+        if self.skips(record) {
+            return Ok(());
+        }
+        self.check_collisions(record)?;
+        let mut encoded = self.encode_fields(record);
+        timestamp::put_timestamp_fields(&mut encoded.payload, when)?;
+        self.send_record_payload(record, &self.finish_payload(encoded))
+            .map(|_| ())
+    }
+
+    /// Send a message with the contents of the file at `path` as field.
+    ///
+    /// Send a record with the given `level`, `target` and `message` like
+    /// [`Self::journal_send`], and add the contents of the file at `path` as
+    /// binary field `field_name`.  The logger escapes `field_name` like the
+    /// names of extra fields.
+    ///
+    /// This is useful to snapshot small files, e.g. configuration files, into
+    /// an entry for diagnostics.
+    ///
+    /// # Errors
+    ///
+    /// Fail if reading the file fails, if the file is larger than the maximum
+    /// field size of journald (768 MiB), or if sending fails.
+    pub fn log_with_file_field(
+        &self,
+        level: Level,
+        target: &str,
+        message: &str,
+        field_name: &str,
+        path: &Path,
+    ) -> Result<(), Error> {
+        let mut contents = Vec::new();
+        std::fs::File::open(path)?
+            .take(MAX_FIELD_SIZE as u64 + 1)
+            .read_to_end(&mut contents)?;
+        if MAX_FIELD_SIZE < contents.len() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "file {} exceeds the maximum field size of {} bytes",
+                    path.display(),
+                    MAX_FIELD_SIZE
+                ),
+            )));
+        }
+        self.send_with_fields(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+            &[(FieldName::WriteEscaped(field_name), &contents)],
+        )
+    }
+
+    /// Send a message with binary `data` as field.
+    ///
+    /// Send a record with the given `level`, `target` and `message` like
+    /// [`Self::journal_send`], and add `data` as binary field `field`, and the
+    /// length of `data` in bytes as `FIELD_LEN` field, where `FIELD` is the
+    /// escaped name of the binary field.  The logger escapes `field` like the
+    /// names of extra fields.
+    ///
+    /// This is useful to attach small binary artifacts, e.g. a packet or a
+    /// certificate, to an entry for diagnostics.
+    ///
+    /// # Errors
+    ///
+    /// Fail if `data` is larger than the maximum field size of journald (768
+    /// MiB), or if sending fails.
+    pub fn log_with_binary(
+        &self,
+        level: Level,
+        target: &str,
+        message: &str,
+        field: &str,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if MAX_FIELD_SIZE < data.len() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "data of {} bytes exceeds the maximum field size of {} bytes",
+                    data.len(),
+                    MAX_FIELD_SIZE
+                ),
+            )));
+        }
+        let name = escape_field_name(field);
+        let len_name = suffix_field_name(&name, b"_LEN");
+        let len = data.len().to_string();
+        self.send_with_fields(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+            &[
+                (FieldName::Escaped(&name), data),
+                (FieldName::Escaped(&len_name), len.as_bytes()),
+            ],
+        )
+    }
+
+    /// Send `record` with additional binary `fields`.
+    fn send_with_fields(
+        &self,
+        record: &Record,
+        fields: &[(FieldName, &[u8])],
+    ) -> Result<(), Error> {
+        if self.skips(record) {
+            return Ok(());
+        }
+        let mut encoded = self.encode_fields(record);
+        for (name, value) in fields {
+            put_field_length_encoded(&mut encoded.payload, *name, *value);
+        }
+        self.send_record_payload(record, &self.finish_payload(encoded))
+            .map(|_| ())
+    }
+
+    /// Timestamp every record with the time returned by `clock`.
+    ///
+    /// If set the logger calls `clock` for every record, and adds the time in
+    /// `SYSLOG_TIMESTAMP` and `SOURCE_REALTIME_TIMESTAMP` like
+    /// [`Self::log_at`].  The logger omits these fields if `clock` returns a
+    /// time before the epoch.  [`Self::log_at`] uses its explicit time instead.
+    ///
+    /// Pass `Arc::new(SystemTime::now)` to timestamp records with the real
+    /// time at which they were logged, or a fixed clock in tests.
+    ///
+    /// By default the logger doesn't add timestamp fields, and journald
+    /// timestamps entries when receiving them.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Log a record with the given `level`, `target`, message `args` and key-values.
+    ///
+    /// Build a [`Record`] and log it like [`Log::log`]; this saves the
+    /// boilerplate of [`Record::builder`] when logging records manually, e.g.
+    /// `logger.emit(Level::Info, "app", format_args!("Hello {}", name), &[])`.
+    pub fn emit(
+        &self,
+        level: Level,
+        target: &str,
+        args: std::fmt::Arguments,
+        kvs: &[(&str, Value)],
+    ) {
+        self.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(args)
+                .key_values(&kvs)
+                .build(),
+        );
+    }
+
+    /// Log a record with the given `level`, `target`, message `args` and `context`.
+    ///
+    /// Log a record like [`Self::emit`], with the fields of `context` as
+    /// key-values, and with the priority of `context`, if any.  This saves
+    /// assembling key-values by hand, e.g.
+    /// `logger.log_with_context(Level::Info, "app", format_args!("Hello"), &JournalContext::new().field("USER", name))`.
+    pub fn log_with_context(
+        &self,
+        level: Level,
+        target: &str,
+        args: std::fmt::Arguments,
+        context: &JournalContext,
+    ) {
+        self.emit(level, target, args, &context.key_values());
+    }
+
+    /// Log a message with the given `level`, `target` and catalog `message_id`.
+    ///
+    /// Log a record with the message `args` like [`Self::emit`], and add
+    /// `message_id` as `MESSAGE_ID` field, formatted as 32 lowercase hex
+    /// digits like `journalctl --new-id128` does.  With a matching entry in
+    /// the systemd message catalog `journalctl -x` then shows help text for
+    /// the entry, see `journalctl(1)` and [the catalog documentation][1].
+    ///
+    /// The logger writes `MESSAGE_ID` right after the standard fields, so the
+    /// limits for key-values (e.g. [`Self::with_max_fields`]) never drop it.
+    ///
+    /// [1]: https://www.freedesktop.org/wiki/Software/systemd/catalog/
+    pub fn log_catalog(
+        &self,
+        level: Level,
+        target: &str,
+        message_id: u128,
+        args: std::fmt::Arguments,
+    ) {
+        let message_id = format!("{:032x}", message_id);
+        self.log_with_logger_fields(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(args)
+                .build(),
+            &[("MESSAGE_ID", message_id.as_bytes())],
+        );
+    }
+
+    /// Log a startup banner for the current process with the given `target`.
+    ///
+    /// Log a record at [`Level::Info`] with the message `starting <identifier>`,
+    /// where `<identifier>` is the syslog identifier of this logger (see
+    /// [`Self::with_syslog_identifier`]), and with the following fields:
+    ///
+    /// - `PID`: The process ID.
+    /// - `VERSION`: The version of the application, if set (see
+    ///   [`Self::with_version`]).
+    /// - `ARGS`: The command line arguments from [`std::env::args_os`],
+    ///   including the program name, separated by spaces.
+    /// - `START_TIME`: The time at which the process started, formatted as RFC
+    ///   3339 timestamp like [`field_datetime`].  The logger omits this field if
+    ///   it can't determine the start time.
+    ///
+    /// The logger writes these fields right after the standard fields, so the
+    /// limits for key-values (e.g. [`Self::with_max_fields`]) never drop them.
+    pub fn log_startup(&self, target: &str) {
+        let pid = std::process::id().to_string();
+        let args = std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let start_time = process_start_time().map(field_datetime);
+        let mut fields = vec![("PID", pid.as_bytes())];
+        if let Some(version) = &self.version {
+            fields.push(("VERSION", version.as_bytes()));
+        }
+        fields.push(("ARGS", args.as_bytes()));
+        if let Some(start_time) = &start_time {
+            fields.push(("START_TIME", start_time.as_bytes()));
+        }
+        self.log_with_logger_fields(
+            &Record::builder()
+                .level(Level::Info)
+                .target(target)
+                .args(format_args!(
+                    "starting {}",
+                    self.settings().syslog_identifier
+                ))
+                .build(),
+            &fields,
+        );
+    }
+
+    /// Get a writer which sends every line written to it as a log record.
+    ///
+    /// The writer buffers written data until a newline, and then sends each
+    /// complete line as a separate record with the given `level` and `target`,
+    /// without the trailing newline.  It sends incomplete trailing data when
+    /// dropped.  Invalid UTF-8 gets replaced.
+    ///
+    /// This is useful to redirect the output of a child process to the journal.
+    ///
+    /// If sending a line fails the writer keeps the line and all lines after
+    /// it, and retries them on the next write or flush.  A write fails without
+    /// accepting data if the retry fails again, and a flush fails if sending
+    /// fails.
+    pub fn line_writer(&self, level: Level, target: String) -> impl Write + '_ {
+        line_writer::LineWriter::new(self, level, target)
+    }
+
+    /// Send a log record with the top-level fields of `value` to the journal.
+    ///
+    /// Serialize `value`, and add each of its top-level fields as a journal
+    /// field, escaped like key-values of records (see [`JournalLog`]).  String
+    /// values appear verbatim, all other values including nested structures
+    /// appear as JSON.
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// # Errors
+    ///
+    /// Fail if `value` doesn't serialize to a struct or map, or if sending fails
+    /// (see [`JournalLog::journal_send`]).
+    #[cfg(feature = "serde")]
+    pub fn log_with_serde<T: serde::Serialize>(
+        &self,
+        level: Level,
+        target: &str,
+        msg: std::fmt::Arguments,
+        value: &T,
+    ) -> Result<(), Error> {
+        let fields = serialize::flatten_fields(value)?;
+        self.journal_send(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(msg)
+                .key_values(&fields.as_slice())
+                .build(),
+        )
+        .map(|_| ())
+    }
+
+    /// Send panics to the journal.
+    ///
+    /// Install a panic hook (see [`std::panic::set_hook`]) which sends every
+    /// panic to the journal, with priority `2` (crit), the panic message as
+    /// `MESSAGE`, the location of the panic in `CODE_FILE` and `CODE_LINE`,
+    /// `panic` as `TARGET`, and a backtrace in `BACKTRACE`.  The panic entry
+    /// also has the syslog identifier and the extra fields of this logger, and
+    /// goes through the field redactor of this logger, if any (see
+    /// [`Self::with_field_redactor`]).
+    ///
+    /// The hook uses the settings of this logger at the time of the panic, so
+    /// it follows [`Self::reconfigure`].  It omits standard fields which the
+    /// logger doesn't write (see [`Self::with_standard_fields`]), and writes
+    /// the target in the field for targets (see
+    /// [`Self::with_logger_field_name`]).
+    ///
+    /// If `chain_previous` is `true` the hook calls the previously installed
+    /// hook afterwards, e.g. to still print the panic to standard error.
+    ///
+    /// If this logger has a custom transport (see [`Self::with_transport`])
+    /// the hook sends panics through that transport.
+    ///
+    /// # Errors
+    ///
+    /// Fail if the journal socket cannot be cloned for the panic hook.
+    pub fn install_panic_hook(&self, chain_previous: bool) -> Result<(), Error> {
+        let transport: Arc<dyn JournalTransport> = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => Arc::new(self.client.try_clone()?),
+        };
+        let logger = panic_hook::PanicLogger {
+            transport,
+            settings: self.settings.clone(),
+            redactor: self.field_redactor.clone(),
+        };
+        panic_hook::install(logger, chain_previous);
+        Ok(())
+    }
+
+    /// Send the `encoded` payload of `record` to the journal.
+    ///
+    /// Call the error hook if key-values of `record` failed to serialize.
+    /// Fall back to syslog if enabled and the journal is unavailable.  Return
+    /// where the record went.
+    fn send_record_payload(&self, record: &Record, encoded: &Encoded) -> Result<Delivery, Error> {
+        self.report_kv_errors(encoded.kv_errors);
+        let payload = encoded.payload.as_slice();
+        self.remember(record, payload);
+        self.deliver(record.level(), payload, || self.syslog_message(record))
+    }
+
+    /// Deliver an encoded `payload` of a record at `level`.
+    ///
+    /// Route `payload` to the namespace of `level` if any, send it, and fall
+    /// back to the syslog message returned by `syslog_message` if enabled and
+    /// the journal is unavailable.  Return where the record went.
+    fn deliver(
+        &self,
+        level: Level,
+        payload: &[u8],
+        syslog_message: impl FnOnce() -> Vec<u8>,
+    ) -> Result<Delivery, Error> {
+        let namespace = self
+            .namespace_router
+            .as_ref()
+            .and_then(|router| Some((router, router.namespace(level)?)));
+        let result = match namespace {
+            Some((router, namespace)) => self.send_to_namespace(router, namespace, payload),
+            None => self.send_payload(payload).map_err(Error::Io),
+        };
+        result.map(|_| Delivery::Journal).or_else(|error| {
+            let unavailable = error.io_error().is_some_and(syslog::journal_unavailable);
+            if self.syslog_fallback && unavailable {
+                let message = syslog_message();
+                Ok(syslog::send(&self.syslog_path, &message).map(|_| Delivery::Syslog)?)
+            } else {
+                Err(error)
+            }
+        })
+    }
+
+    /// Format `record` as syslog message for the syslog fallback.
+    fn syslog_message(&self, record: &Record) -> Vec<u8> {
+        let now = self
+            .clock
+            .as_ref()
+            .map_or_else(SystemTime::now, |clock| clock());
+        let priority = u8::from(self.record_priority(record));
+        let settings = self.settings();
+        let identifier = self.syslog_identifier(&settings, record);
+        syslog::format_record(priority, identifier, record, now)
+    }
+
+    /// Log `record` like [`Log::log`], with additional `logger_fields`.
+    ///
+    /// Unlike key-values of `record`, `logger_fields` are not subject to the
+    /// limits and policies for key-values, e.g. [`Self::with_max_fields`].
+    fn log_with_logger_fields(&self, record: &Record, logger_fields: LoggerFields) {
+        if !self.enabled(record.metadata()) || self.skips(record) {
+            return;
+        }
+        // We can't really handle errors here, so simply discard them.
+        // The alternative would be to panic, but a failed logging call should
+        // not bring the entire process down.
+        let _ = self.check_collisions(record).and_then(|_| {
+            let encoded = self.encode_record(record, logger_fields, EncodeMode::Encode);
+            self.send_record_payload(record, &encoded)
+        });
+        self.tee(record);
+    }
+
+    /// Write `record` to standard error, the kernel log, and the JSON writer, if enabled.
+    fn tee(&self, record: &Record) {
+        if self.tee_stderr {
+            let _ = stderr::write_record(record);
+        }
+        if self.kmsg_tee {
+            let priority = u8::from(self.record_priority(record));
+            let settings = self.settings();
+            let identifier = self.syslog_identifier(&settings, record);
+            let line = kmsg::format_record(priority, identifier, std::process::id(), record);
+            if let Err(error) = kmsg::write(&line) {
+                self.report_error(&error);
+            }
+        }
+        #[cfg(feature = "serde")]
+        if let Some(writer) = &self.json_tee {
+            let priority = u8::from(self.record_priority(record));
+            let mut writer = sync::lock_ignoring_poison(writer);
+            let _ = serialize::write_json_record(&mut *writer, priority, record);
+        }
+    }
+
+    /// Send an encoded `payload` to the `namespace` selected by `router`.
+    ///
+    /// Send through the custom transport if any, and otherwise through the
+    /// client of `namespace`.  Call the error hook if `namespace` is invalid,
+    /// if the client for `namespace` can't be created, or if sending fails.
+    fn send_to_namespace(
+        &self,
+        router: &namespace::NamespaceRouter,
+        namespace: Result<String, Error>,
+        payload: &[u8],
+    ) -> Result<(), Error> {
+        let namespace = self.report_route_error(namespace)?;
+        match &self.transport {
+            Some(transport) => Ok(self.count_transport_send(
+                self.report_send_error(transport.send_to_namespace(&namespace, payload)),
+            )?),
+            None => {
+                let client = self.report_route_error(router.client(&self.client, namespace))?;
+                Ok(self.report_send_error(client.send_payload(payload).map(|_| ()))?)
+            }
+        }
+    }
+
+    /// Send an encoded `payload` to the default journal.
+    ///
+    /// Send through the custom transport if any, and otherwise through the
+    /// client.  Call the error hook if sending fails.
+    fn send_payload(&self, payload: &[u8]) -> std::io::Result<()> {
+        match &self.transport {
+            Some(transport) => {
+                self.count_transport_send(self.report_send_error(transport.send(payload)))
+            }
+            None => self.report_send_error(self.client.send_payload(payload).map(|_| ())),
+        }
+    }
+
+    /// Count the `result` of sending through the custom transport in the diagnostics.
+    fn count_transport_send(&self, result: std::io::Result<()>) -> std::io::Result<()> {
+        match result {
+            Ok(_) => self.client.count_sent(),
+            Err(_) => self.client.count_dropped(),
+        }
+        result
+    }
+
+    /// Call the error hook if the `result` of sending failed.
+    fn report_send_error(&self, result: std::io::Result<()>) -> std::io::Result<()> {
+        if let Err(error) = &result {
+            self.report_error(error);
+        }
+        result
+    }
+
+    /// Call the error hook if routing to a namespace failed.
+    ///
+    /// Pass the underlying I/O error to the hook, or an I/O error of kind
+    /// [`std::io::ErrorKind::InvalidInput`] for an invalid namespace.
+    fn report_route_error<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(error) = &result {
+            match error.io_error() {
+                Some(error) => self.report_error(error),
+                None => self.report_error(&std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    error.to_string(),
+                )),
+            }
+        }
+        result
+    }
+
+    /// Remember the encoded `payload` of `record` in the ring buffer, if enabled.
+    fn remember(&self, record: &Record, payload: &[u8]) {
+        if let Some(ring_buffer) = &self.ring_buffer {
+            ring_buffer.push(record, payload);
+        }
+    }
+
+    /// Call the error hook, if any, if `errors` key-values failed to serialize.
+    fn report_kv_errors(&self, errors: usize) {
+        if 0 < errors {
+            self.report_error(&std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("key-values failed to serialize (KV_ERRORS={})", errors),
+            ));
+        }
+    }
+
+    /// Call the error hook, if any, with `error`.
+    fn report_error(&self, error: &std::io::Error) {
+        if let Some(on_error) = &self.on_error {
+            // Don't let a faulty hook take down the logger
+            let _ = std::panic::catch_unwind(AssertUnwindSafe(|| on_error(error)));
+        }
+    }
+}
+
+// The Log trait requires Send and Sync, but we'd like to be explicit about it.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<JournalLog>();
+};
+
+/// The [`Log`] interface for [`JournalLog`].
+impl Log for JournalLog {
+    /// Whether this logger is enabled.
+    ///
+    /// See [`JournalLog::would_log`].
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.would_log(metadata.level(), metadata.target())
+    }
+
+    /// Send the given `record` to the systemd journal.
+    ///
+    /// Also write the `record` to standard error if enabled with
+    /// [`JournalLog::with_tee_stderr`].
+    ///
+    /// Discard the `record` if the empty message policy says so (see
+    /// [`JournalLog::with_empty_message_policy`]), or if sampling discards it
+    /// (see [`JournalLog::with_sampling`]).
+    ///
+    /// # Errors
+    ///
+    /// Ignore any errors which occur when sending `record` to journald because
+    /// we cannot reasonably handle them at this place.
+    ///
+    /// See [`JournalLog::journal_send`] for a function which returns any error
+    /// which might have occurred while sending the `record` to the journal.
+    fn log(&self, record: &Record) {
+        self.log_with_logger_fields(record, &[]);
+    }
+
+    /// Flush log records.
+    ///
+    /// The logger sends each record to the journal right away, so this only
+    /// flushes standard error if enabled with [`JournalLog::with_tee_stderr`].
+    fn flush(&self) {
+        if self.tee_stderr {
+            let _ = std::io::stderr().flush();
+        }
+    }
 }
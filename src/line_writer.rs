@@ -0,0 +1,100 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Write lines of output as log records.
+
+use std::io::prelude::*;
+
+use log::{Level, Record};
+
+use crate::JournalLog;
+
+/// A writer which sends every line as a separate log record.
+pub struct LineWriter<'a> {
+    logger: &'a JournalLog,
+    level: Level,
+    target: String,
+    /// Data after the last complete line.
+    buffer: Vec<u8>,
+}
+
+impl<'a> LineWriter<'a> {
+    pub fn new(logger: &'a JournalLog, level: Level, target: String) -> Self {
+        Self {
+            logger,
+            level,
+            target,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Send all complete lines in the buffer.
+    ///
+    /// Remove lines from the buffer only after sending them, to keep a line
+    /// which failed to send, and all lines after it, for the next attempt.
+    fn send_complete_lines(&mut self) -> std::io::Result<()> {
+        let mut sent = 0;
+        let result = loop {
+            match self.buffer[sent..].iter().position(|b| *b == b'\n') {
+                Some(end) => match self.send_line(&self.buffer[sent..sent + end]) {
+                    Ok(()) => sent += end + 1,
+                    Err(error) => break Err(error),
+                },
+                None => break Ok(()),
+            }
+        };
+        self.buffer.drain(..sent);
+        result
+    }
+
+    /// Send `line` as log record, without any trailing newline.
+    fn send_line(&self, line: &[u8]) -> std::io::Result<()> {
+        self.logger
+            .journal_send(
+                &Record::builder()
+                    .level(self.level)
+                    .target(&self.target)
+                    .args(format_args!("{}", String::from_utf8_lossy(line)))
+                    .build(),
+            )
+            .map(|_| ())
+            .map_err(std::io::Error::from)
+    }
+}
+
+impl<'a> Write for LineWriter<'a> {
+    /// Write `buf`, and send all complete lines.
+    ///
+    /// First retry lines which failed to send before, and fail without
+    /// accepting `buf` if that fails again.  Then accept all of `buf`; lines
+    /// of `buf` which fail to send stay in the buffer, and the next write or
+    /// flush reports the error.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.send_complete_lines()?;
+        self.buffer.extend_from_slice(buf);
+        // We accepted buf already, so we must not fail; see above
+        let _ = self.send_complete_lines();
+        Ok(buf.len())
+    }
+
+    /// Send all complete lines which failed to send before.
+    ///
+    /// The writer only sends incomplete trailing data when dropped.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_complete_lines()
+    }
+}
+
+impl<'a> Drop for LineWriter<'a> {
+    fn drop(&mut self) {
+        // We can't handle errors in drop
+        if self.send_complete_lines().is_ok() && !self.buffer.is_empty() {
+            let _ = self.send_line(&self.buffer);
+        }
+    }
+}
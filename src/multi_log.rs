@@ -0,0 +1,76 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Send log records to the journal and other loggers.
+
+use log::{Log, Metadata, Record, SetLoggerError};
+
+use crate::JournalLog;
+
+/// A logger which sends records to the journal and other loggers.
+///
+/// This logger forwards every record to a [`JournalLog`] and to all loggers
+/// added with [`MultiLog::with_logger`], e.g. to additionally write records to
+/// a file.
+pub struct MultiLog {
+    journal: JournalLog,
+    others: Vec<Box<dyn Log>>,
+}
+
+impl MultiLog {
+    /// Create a logger which forwards records to `journal`.
+    pub fn new(journal: JournalLog) -> Self {
+        Self {
+            journal,
+            others: Vec::new(),
+        }
+    }
+
+    /// Also forward records to `logger`.
+    pub fn with_logger(mut self, logger: Box<dyn Log>) -> Self {
+        self.others.push(logger);
+        self
+    }
+
+    /// Install this logger globally.
+    ///
+    /// See [`log::set_boxed_logger`].
+    pub fn install(self) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+/// The [`Log`] interface for [`MultiLog`].
+impl Log for MultiLog {
+    /// Whether any of the wrapped loggers is enabled for `metadata`.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.journal.enabled(metadata) || self.others.iter().any(|l| l.enabled(metadata))
+    }
+
+    /// Send `record` to the journal and all other loggers.
+    ///
+    /// Only forward `record` to other loggers which are enabled for it.
+    fn log(&self, record: &Record) {
+        if self.journal.enabled(record.metadata()) {
+            self.journal.log(record);
+        }
+        for logger in &self.others {
+            if logger.enabled(record.metadata()) {
+                logger.log(record);
+            }
+        }
+    }
+
+    /// Flush all wrapped loggers.
+    fn flush(&self) {
+        self.journal.flush();
+        for logger in &self.others {
+            logger.flush();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Route records to journal namespaces.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::Level;
+
+use crate::client::JournalClient;
+use crate::sync::lock_ignoring_poison;
+use crate::Error;
+
+/// A function selecting the namespace for a log level.
+pub type Route = Box<dyn Fn(Level) -> Option<String> + Send + Sync>;
+
+/// Get the socket path of `namespace`, relative to the `default` socket path.
+///
+/// journald listens for namespace `foo` on `/run/systemd/journal.foo/socket`,
+/// next to the default socket `/run/systemd/journal/socket`; we apply the same
+/// scheme to any other default socket path.
+pub fn namespace_socket_path(default: &Path, namespace: &str) -> PathBuf {
+    let directory = default.parent().unwrap_or(Path::new(""));
+    let mut name = OsString::from(directory.file_name().unwrap_or_default());
+    name.push(".");
+    name.push(namespace);
+    let socket = default.file_name().unwrap_or_default();
+    directory.with_file_name(name).join(socket)
+}
+
+/// Whether `namespace` is a valid journal namespace.
+///
+/// Like journald require a non-empty name of ASCII letters, digits, and the
+/// characters `:_.-`, which doesn't start with a dot.
+pub fn is_valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty()
+        && !namespace.starts_with('.')
+        && namespace
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || b":_.-".contains(&c))
+}
+
+/// Routes records to namespaces, with a cache of clients per namespace.
+pub struct NamespaceRouter {
+    route: Route,
+    clients: Mutex<HashMap<String, Arc<JournalClient>>>,
+}
+
+impl NamespaceRouter {
+    pub fn new(route: Route) -> Self {
+        Self {
+            route,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check that the route returns a valid namespace for every level.
+    ///
+    /// Fail with [`Error::InvalidNamespace`] for the first invalid namespace.
+    pub fn validate(&self) -> Result<(), Error> {
+        for level in Level::iter() {
+            if let Some(namespace) = (self.route)(level) {
+                if !is_valid_namespace(&namespace) {
+                    return Err(Error::InvalidNamespace(namespace));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the namespace of `level`, if any.
+    ///
+    /// Return `None` if `level` goes to the default namespace, and
+    /// [`Error::InvalidNamespace`] if the route returned an invalid namespace.
+    pub fn namespace(&self, level: Level) -> Option<Result<String, Error>> {
+        let namespace = (self.route)(level)?;
+        if is_valid_namespace(&namespace) {
+            Some(Ok(namespace))
+        } else {
+            Some(Err(Error::InvalidNamespace(namespace)))
+        }
+    }
+
+    /// Get a client for the valid `namespace`.
+    ///
+    /// Derive clients for namespaces from `default` on first use, and cache
+    /// them afterwards.  Create new clients outside of the cache lock, so that
+    /// a slow socket doesn't hold up records for other namespaces; if two
+    /// threads race for the same namespace the first client to arrive wins.
+    pub fn client(
+        &self,
+        default: &JournalClient,
+        namespace: String,
+    ) -> Result<Arc<JournalClient>, Error> {
+        if let Some(client) = lock_ignoring_poison(&self.clients).get(&namespace) {
+            return Ok(client.clone());
+        }
+        let mut client = default.try_clone().map_err(Error::Io)?;
+        client.set_path(namespace_socket_path(default.path(), &namespace));
+        Ok(lock_ignoring_poison(&self.clients)
+            .entry(namespace)
+            .or_insert_with(|| Arc::new(client))
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn namespace_socket_path() {
+        assert_eq!(
+            super::namespace_socket_path(Path::new("/run/systemd/journal/socket"), "foo"),
+            Path::new("/run/systemd/journal.foo/socket")
+        );
+        assert_eq!(
+            super::namespace_socket_path(Path::new("/tmp/test/journal/sock"), "errors"),
+            Path::new("/tmp/test/journal.errors/sock")
+        );
+    }
+
+    #[test]
+    fn is_valid_namespace() {
+        assert!(super::is_valid_namespace("foo"));
+        assert!(super::is_valid_namespace("foo-bar_1.2:3"));
+        assert!(!super::is_valid_namespace(""));
+        assert!(!super::is_valid_namespace("."));
+        assert!(!super::is_valid_namespace(".."));
+        assert!(!super::is_valid_namespace("foo/bar"));
+        assert!(!super::is_valid_namespace("foo bar"));
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! OpenTelemetry integration.
+
+use std::io::Write;
+
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+
+/// Put the trace and span id of the current OpenTelemetry context into `buffer`.
+///
+/// Write nothing if there's no valid span context.
+pub fn put_current_span_context(buffer: &mut Vec<u8>) {
+    let context = Context::current();
+    let span = context.span();
+    let span_context = span.span_context();
+    if span_context.is_valid() {
+        // Ids are formatted as lowercase hex digits, so we can write them
+        // directly without escaping or length-encoding.
+        writeln!(buffer, "TRACE_ID={}", span_context.trace_id()).unwrap();
+        writeln!(buffer, "SPAN_ID={}", span_context.span_id()).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Send panics to the journal.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::borrow::Cow;
+use std::panic::Location;
+use std::sync::Arc;
+
+use crate::config::SharedSettings;
+use crate::fields::*;
+use crate::redact::{self, Redactor};
+use crate::sync::read_ignoring_poison;
+use crate::transport::JournalTransport;
+use crate::StandardFields;
+
+/// Everything we need to send a panic to the journal.
+pub struct PanicLogger {
+    pub transport: Arc<dyn JournalTransport>,
+    /// The settings of the logger, read at the time of a panic.
+    pub(crate) settings: SharedSettings,
+    pub redactor: Option<Redactor>,
+}
+
+/// Get the message of a panic `payload`.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+impl PanicLogger {
+    /// Encode a panic with the given `payload` and `location`.
+    ///
+    /// Write the standard fields of the logger with the current settings, and
+    /// pass all fields through the redactor, if any.
+    pub fn encode(
+        &self,
+        payload: &(dyn Any + Send),
+        location: Option<&Location>,
+        backtrace: &Backtrace,
+    ) -> Vec<u8> {
+        let settings = read_ignoring_poison(&self.settings).clone();
+        let fields = settings.standard_fields;
+        let mut pairs: Vec<(&str, Cow<[u8]>)> = Vec::with_capacity(8);
+        if fields.contains(StandardFields::PRIORITY) {
+            // Panics get a higher priority than any log record: 2 (crit)
+            pairs.push(("PRIORITY", Cow::Borrowed(b"2")));
+        }
+        if fields.contains(StandardFields::MESSAGE) {
+            pairs.push(("MESSAGE", Cow::Borrowed(panic_message(payload).as_bytes())));
+        }
+        if fields.contains(StandardFields::SYSLOG_IDENTIFIER)
+            && !settings.syslog_identifier.is_empty()
+        {
+            pairs.push((
+                "SYSLOG_IDENTIFIER",
+                Cow::Borrowed(settings.syslog_identifier.as_bytes()),
+            ));
+        }
+        if fields.contains(StandardFields::SYSLOG_PID) {
+            let pid = std::process::id().to_string().into_bytes();
+            pairs.push(("SYSLOG_PID", Cow::Owned(pid)));
+        }
+        if let Some(location) = location {
+            if fields.contains(StandardFields::CODE_FILE) {
+                pairs.push(("CODE_FILE", Cow::Borrowed(location.file().as_bytes())));
+            }
+            if fields.contains(StandardFields::CODE_LINE) {
+                let line = location.line().to_string().into_bytes();
+                pairs.push(("CODE_LINE", Cow::Owned(line)));
+            }
+        }
+        if fields.contains(StandardFields::TARGET) {
+            pairs.push((&settings.target_field_name, Cow::Borrowed(b"panic")));
+        }
+        let mut buffer = Vec::with_capacity(4096);
+        put_standard_fields(&mut buffer, pairs);
+        put_field_length_encoded(
+            &mut buffer,
+            FieldName::WellFormed("BACKTRACE"),
+            backtrace.to_string().as_bytes(),
+        );
+        buffer.extend_from_slice(&settings.extra_fields);
+        match &self.redactor {
+            Some(redactor) => redact::redact(&buffer, redactor),
+            None => buffer,
+        }
+    }
+}
+
+/// Install a panic hook which sends panics with `logger`.
+///
+/// If `chain_previous` is `true` call the previous hook afterwards.
+pub fn install(logger: PanicLogger, chain_previous: bool) {
+    let previous = if chain_previous {
+        Some(std::panic::take_hook())
+    } else {
+        None
+    };
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::force_capture();
+        let payload = logger.encode(info.payload(), info.location(), &backtrace);
+        // We're panicking already, so there's nothing we can do about errors.
+        let _ = logger.transport.send(&payload);
+        if let Some(previous) = &previous {
+            previous(info);
+        }
+    }));
+}
@@ -0,0 +1,193 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Journal priorities.
+
+use log::Level;
+
+/// A journal priority, i.e. a syslog severity.
+///
+/// See `PRIORITY=` in [`systemd.journal-fields(7)`](https://www.freedesktop.org/software/systemd/man/systemd.journal-fields.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Priority {
+    /// The system is unusable (`emerg`).
+    Emergency = 0,
+    /// Action must be taken immediately (`alert`).
+    Alert = 1,
+    /// Critical conditions (`crit`).
+    Critical = 2,
+    /// Error conditions (`err`).
+    Error = 3,
+    /// Warning conditions (`warning`).
+    Warning = 4,
+    /// Normal, but significant conditions (`notice`).
+    Notice = 5,
+    /// Informational messages (`info`).
+    Info = 6,
+    /// Debug-level messages (`debug`).
+    Debug = 7,
+}
+
+impl Priority {
+    /// Get the priority with the given syslog severity `name`, e.g. `crit`.
+    pub fn from_severity_name(name: &str) -> Option<Self> {
+        match name {
+            "emerg" => Some(Self::Emergency),
+            "alert" => Some(Self::Alert),
+            "crit" => Some(Self::Critical),
+            "err" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "notice" => Some(Self::Notice),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    /// Get the syslog severity name of this priority, e.g. `crit`.
+    ///
+    /// This is the inverse of [`Self::from_severity_name`].
+    pub fn severity_name(self) -> &'static str {
+        match self {
+            Self::Emergency => "emerg",
+            Self::Alert => "alert",
+            Self::Critical => "crit",
+            Self::Error => "err",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+
+    /// Get the priority with the numeric `value`, if any.
+    pub(crate) fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Emergency),
+            1 => Some(Self::Alert),
+            2 => Some(Self::Critical),
+            3 => Some(Self::Error),
+            4 => Some(Self::Warning),
+            5 => Some(Self::Notice),
+            6 => Some(Self::Info),
+            7 => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    /// The value of the `PRIORITY` field for this priority.
+    pub(crate) fn as_field_value(self) -> &'static [u8] {
+        match self {
+            Self::Emergency => b"0",
+            Self::Alert => b"1",
+            Self::Critical => b"2",
+            Self::Error => b"3",
+            Self::Warning => b"4",
+            Self::Notice => b"5",
+            Self::Info => b"6",
+            Self::Debug => b"7",
+        }
+    }
+}
+
+/// Map log levels to priorities as documented for [`crate::JournalLog`].
+///
+/// The mapping is total: [`Level`] isn't `#[non_exhaustive]`, so the compiler
+/// checks that every level has a priority, and a new level in `log` would
+/// fail to build instead of panicking at runtime.
+impl From<Level> for Priority {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => Self::Error,
+            Level::Warn => Self::Warning,
+            Level::Info => Self::Notice,
+            Level::Debug => Self::Info,
+            Level::Trace => Self::Debug,
+        }
+    }
+}
+
+impl From<Priority> for u8 {
+    fn from(priority: Priority) -> Self {
+        priority as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn from_level() {
+        let priorities: Vec<Priority> = [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ]
+        .into_iter()
+        .map(Priority::from)
+        .collect();
+        assert_eq!(
+            priorities,
+            vec![
+                Priority::Error,
+                Priority::Warning,
+                Priority::Notice,
+                Priority::Info,
+                Priority::Debug
+            ]
+        );
+    }
+
+    #[test]
+    fn from_level_is_total() {
+        // Exhaustively match, to fail to build if log ever adds a level
+        for level in Level::iter() {
+            let expected = match level {
+                Level::Error => b"3",
+                Level::Warn => b"4",
+                Level::Info => b"5",
+                Level::Debug => b"6",
+                Level::Trace => b"7",
+            };
+            assert_eq!(Priority::from(level).as_field_value(), expected);
+        }
+        // A record built without an explicit level gets the default level of log
+        let record = log::Record::builder().build();
+        assert_eq!(Priority::from(record.level()), Priority::Notice);
+    }
+
+    #[test]
+    fn to_u8() {
+        assert_eq!(u8::from(Priority::Emergency), 0);
+        assert_eq!(u8::from(Priority::Warning), 4);
+        assert_eq!(u8::from(Priority::Debug), 7);
+        assert_eq!(Priority::Critical.as_field_value(), b"2");
+        assert_eq!(Priority::from_u8(4), Some(Priority::Warning));
+        assert_eq!(Priority::from_u8(8), None);
+    }
+
+    #[test]
+    fn from_severity_name() {
+        assert_eq!(
+            Priority::from_severity_name("crit"),
+            Some(Priority::Critical)
+        );
+        assert_eq!(Priority::from_severity_name("Crit"), None);
+        for priority in [Priority::Emergency, Priority::Warning, Priority::Debug] {
+            assert_eq!(
+                Priority::from_severity_name(priority.severity_name()),
+                Some(priority)
+            );
+        }
+    }
+}
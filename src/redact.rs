@@ -0,0 +1,115 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Redact fields of encoded journal entries.
+
+use std::sync::Arc;
+
+use crate::fields::{put_field_bytes, put_field_length_encoded, FieldName};
+
+/// A function which drops or replaces the value of a field.
+///
+/// Shared between a logger and its panic hook.
+pub type Redactor = Arc<dyn Fn(&str, &[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// A field of an encoded journal entry.
+pub struct Field<'a> {
+    pub name: &'a [u8],
+    pub value: &'a [u8],
+    length_encoded: bool,
+}
+
+/// Split the first field off `payload`.
+///
+/// Return the field and the rest of `payload`, or `None` if `payload` is
+/// empty or malformed.
+pub fn split_field(payload: &[u8]) -> Option<(Field<'_>, &[u8])> {
+    let end_of_name = payload.iter().position(|&c| c == b'=' || c == b'\n')?;
+    let (name, rest) = payload.split_at(end_of_name);
+    if rest[0] == b'=' {
+        let rest = &rest[1..];
+        let end_of_value = rest.iter().position(|&c| c == b'\n')?;
+        let field = Field {
+            name,
+            value: &rest[..end_of_value],
+            length_encoded: false,
+        };
+        Some((field, &rest[end_of_value + 1..]))
+    } else {
+        let rest = &rest[1..];
+        let length = u64::from_le_bytes(rest.get(..8)?.try_into().ok()?) as usize;
+        let value = rest.get(8..8 + length)?;
+        let field = Field {
+            name,
+            value,
+            length_encoded: true,
+        };
+        Some((field, rest.get(8 + length + 1..)?))
+    }
+}
+
+/// Pass every field of the encoded `payload` through `redactor`.
+///
+/// Drop fields for which `redactor` returns `None`, and replace the value
+/// with the returned bytes otherwise.  Keep the encoding of fields, unless a
+/// new value has newlines which require length encoding.  Copy any malformed
+/// rest of `payload` verbatim.
+pub fn redact(payload: &[u8], redactor: &Redactor) -> Vec<u8> {
+    let mut redacted = Vec::with_capacity(payload.len());
+    let mut rest = payload;
+    while let Some((field, tail)) = split_field(rest) {
+        rest = tail;
+        // We only ever write ASCII field names
+        let name = String::from_utf8_lossy(field.name);
+        if let Some(value) = redactor(&name, field.value) {
+            let name = FieldName::Escaped(field.name);
+            if field.length_encoded {
+                put_field_length_encoded(&mut redacted, name, value.as_slice());
+            } else {
+                put_field_bytes(&mut redacted, name, &value);
+            }
+        }
+    }
+    redacted.extend_from_slice(rest);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn redact() {
+        let mut payload = Vec::new();
+        put_field_bytes(&mut payload, FieldName::WellFormed("PRIORITY"), b"6");
+        put_field_length_encoded(
+            &mut payload,
+            FieldName::WellFormed("MESSAGE"),
+            b"a\nmessage".as_slice(),
+        );
+        put_field_bytes(&mut payload, FieldName::WellFormed("TOKEN"), b"secret");
+        put_field_bytes(&mut payload, FieldName::WellFormed("DROP"), b"me");
+        let redactor: Redactor = Arc::new(|name, value| match name {
+            "TOKEN" => Some(b"****".to_vec()),
+            "DROP" => None,
+            _ => Some(value.to_vec()),
+        });
+
+        let mut expected = Vec::new();
+        put_field_bytes(&mut expected, FieldName::WellFormed("PRIORITY"), b"6");
+        put_field_length_encoded(
+            &mut expected,
+            FieldName::WellFormed("MESSAGE"),
+            b"a\nmessage".as_slice(),
+        );
+        put_field_bytes(&mut expected, FieldName::WellFormed("TOKEN"), b"****");
+        assert_eq!(super::redact(&payload, &redactor), expected);
+    }
+}
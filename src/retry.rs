@@ -0,0 +1,99 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Retry transient send failures.
+
+use std::io::{ErrorKind, Result};
+use std::time::Duration;
+
+/// How often to retry transient failures.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    /// The number of retries.
+    pub attempts: usize,
+    /// The time to wait before each retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Run `op` and retry transient failures according to this policy.
+    ///
+    /// Retry `EAGAIN` up to `attempts` times and wait for `backoff` before
+    /// each retry.  Always retry `EINTR` immediately.
+    pub fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut retries = 0;
+        loop {
+            match op() {
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) if error.kind() == ErrorKind::WouldBlock && retries < self.attempts => {
+                    retries += 1;
+                    std::thread::sleep(self.backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind};
+    use std::time::Duration;
+
+    use similar_asserts::assert_eq;
+
+    use super::RetryPolicy;
+
+    /// A fake send operation which fails with `errors` first.
+    fn flaky_send(mut errors: Vec<ErrorKind>) -> impl FnMut() -> std::io::Result<usize> {
+        errors.reverse();
+        move || match errors.pop() {
+            Some(kind) => Err(Error::from(kind)),
+            None => Ok(42),
+        }
+    }
+
+    #[test]
+    fn retry_succeeds_on_second_attempt() {
+        let policy = RetryPolicy {
+            attempts: 1,
+            backoff: Duration::from_millis(1),
+        };
+        let result = policy.run(flaky_send(vec![ErrorKind::WouldBlock]));
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[test]
+    fn retry_gives_up_after_attempts() {
+        let policy = RetryPolicy {
+            attempts: 2,
+            backoff: Duration::ZERO,
+        };
+        let errors = vec![ErrorKind::WouldBlock; 3];
+        let error = policy.run(flaky_send(errors)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn retry_always_retries_interrupted() {
+        let policy = RetryPolicy::default();
+        let errors = vec![ErrorKind::Interrupted; 5];
+        assert_eq!(policy.run(flaky_send(errors)).unwrap(), 42);
+    }
+
+    #[test]
+    fn retry_does_not_retry_other_errors() {
+        let policy = RetryPolicy {
+            attempts: 5,
+            backoff: Duration::ZERO,
+        };
+        let errors = vec![ErrorKind::ConnectionRefused];
+        let error = policy.run(flaky_send(errors)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    }
+}
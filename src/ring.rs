@@ -0,0 +1,93 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Keep the most recent entries of a logger in memory.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use log::{Level, Record};
+
+use crate::redact::split_field;
+use crate::sync::lock_ignoring_poison;
+
+/// A recent entry of a logger.
+///
+/// See [`crate::JournalLog::recent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EntrySnapshot {
+    /// The level of the record.
+    pub level: Level,
+    /// The target of the record.
+    pub target: String,
+    /// The entry in the native journal protocol, as sent to the journal.
+    pub payload: Vec<u8>,
+}
+
+impl EntrySnapshot {
+    /// Decode the names and values of all fields of this entry, in order.
+    pub fn fields(&self) -> Vec<(String, Vec<u8>)> {
+        let mut fields = Vec::new();
+        let mut rest = self.payload.as_slice();
+        while let Some((field, tail)) = split_field(rest) {
+            rest = tail;
+            fields.push((
+                String::from_utf8_lossy(field.name).into_owned(),
+                field.value.to_vec(),
+            ));
+        }
+        fields
+    }
+
+    /// Get the value of the first field `name` of this entry, if any.
+    pub fn field(&self, name: &str) -> Option<Vec<u8>> {
+        self.fields()
+            .into_iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A bounded buffer of the most recent entries.
+pub struct RingBuffer {
+    capacity: usize,
+    entries: Mutex<VecDeque<EntrySnapshot>>,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<EntrySnapshot>> {
+        lock_ignoring_poison(&self.entries)
+    }
+
+    /// Remember the encoded `payload` of `record`, and forget the oldest entry if full.
+    pub fn push(&self, record: &Record, payload: &[u8]) {
+        let snapshot = EntrySnapshot {
+            level: record.level(),
+            target: record.target().to_string(),
+            payload: payload.to_vec(),
+        };
+        let mut entries = self.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(snapshot);
+    }
+
+    /// Get all remembered entries, oldest first.
+    pub fn entries(&self) -> Vec<EntrySnapshot> {
+        self.lock().iter().cloned().collect()
+    }
+}
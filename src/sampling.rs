@@ -0,0 +1,103 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Sample verbose records to reduce volume.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use log::{Level, Record};
+
+use crate::sync::lock_ignoring_poison;
+
+/// Keep only every nth record at or below a level, per target.
+pub struct Sampler {
+    level: Level,
+    keep_every: u32,
+    /// The number of sampled records seen so far, per target.
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl Sampler {
+    pub fn new(level: Level, keep_every: u32) -> Self {
+        Self {
+            level,
+            keep_every: keep_every.max(1),
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The number of records of which the sampler keeps one.
+    pub fn keep_every(&self) -> u32 {
+        self.keep_every
+    }
+
+    /// Whether records with `level` are subject to sampling.
+    ///
+    /// A sampler which keeps every record samples nothing.
+    pub fn samples(&self, level: Level) -> bool {
+        1 < self.keep_every && self.level <= level
+    }
+
+    /// Count `record` and return whether to keep it.
+    ///
+    /// Always keep records above the level of this sampler; of the other
+    /// records keep the first and then every nth record of each target.
+    pub fn keeps(&self, record: &Record) -> bool {
+        if !self.samples(record.level()) {
+            return true;
+        }
+        let mut counts = lock_ignoring_poison(&self.counts);
+        let count = match counts.get_mut(record.target()) {
+            Some(count) => count,
+            None => counts.entry(record.target().to_string()).or_insert(0),
+        };
+        let keep = *count % u64::from(self.keep_every) == 0;
+        *count += 1;
+        keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    fn kept(sampler: &Sampler, level: Level, target: &str, count: usize) -> usize {
+        let record = Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("sampled"))
+            .build();
+        (0..count).filter(|_| sampler.keeps(&record)).count()
+    }
+
+    #[test]
+    fn keeps_every_nth_record_per_target() {
+        let sampler = Sampler::new(Level::Debug, 10);
+        assert_eq!(kept(&sampler, Level::Debug, "foo", 25), 3);
+        assert_eq!(kept(&sampler, Level::Trace, "bar", 10), 1);
+        // The count of foo continues where it left off, and keeps the 30th record
+        assert_eq!(kept(&sampler, Level::Debug, "foo", 4), 0);
+        assert_eq!(kept(&sampler, Level::Debug, "foo", 2), 1);
+    }
+
+    #[test]
+    fn keeps_all_records_above_level() {
+        let sampler = Sampler::new(Level::Debug, 10);
+        assert_eq!(kept(&sampler, Level::Info, "foo", 25), 25);
+    }
+
+    #[test]
+    fn keep_every_zero_keeps_all_records() {
+        let sampler = Sampler::new(Level::Debug, 0);
+        assert_eq!(sampler.keep_every(), 1);
+        assert!(!sampler.samples(Level::Debug));
+        assert_eq!(kept(&sampler, Level::Debug, "foo", 5), 5);
+    }
+}
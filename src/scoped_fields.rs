@@ -0,0 +1,63 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fields scoped to a closure on the current thread.
+
+use std::cell::RefCell;
+
+use crate::fields::{escape_field_name, put_field_bytes, FieldName};
+
+thread_local! {
+    /// The escaped names and encoded fields of the scoped fields of the current thread.
+    ///
+    /// Nested scopes append their fields, and truncate the fields again when
+    /// they end, so the fields work as a stack.
+    static SCOPED_FIELDS: RefCell<Vec<(Vec<u8>, Vec<u8>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Removes the fields of a scope when dropped, even when unwinding.
+struct PopOnDrop {
+    /// The number of fields before the scope started.
+    len: usize,
+}
+
+impl Drop for PopOnDrop {
+    fn drop(&mut self) {
+        SCOPED_FIELDS.with(|fields| fields.borrow_mut().truncate(self.len));
+    }
+}
+
+/// Run `f` with `fields` added to the scoped fields of the current thread.
+pub fn with_scoped_fields<R>(fields: &[(String, String)], f: impl FnOnce() -> R) -> R {
+    let _pop = SCOPED_FIELDS.with(|scoped| {
+        let mut scoped = scoped.borrow_mut();
+        let len = scoped.len();
+        for (name, value) in fields {
+            let mut field = Vec::new();
+            put_field_bytes(&mut field, FieldName::WriteEscaped(name), value.as_bytes());
+            scoped.push((escape_field_name(name), field));
+        }
+        PopOnDrop { len }
+    });
+    f()
+}
+
+/// Put all scoped fields of the current thread into `buffer`.
+///
+/// Put fields of outer scopes first, and omit fields which a nested scope
+/// overrides with a field of the same escaped name.
+pub fn put_scoped_fields(buffer: &mut Vec<u8>) {
+    SCOPED_FIELDS.with(|fields| {
+        let fields = fields.borrow();
+        for (i, (name, field)) in fields.iter().enumerate() {
+            if !fields[i + 1..].iter().any(|(inner, _)| inner == name) {
+                buffer.extend_from_slice(field);
+            }
+        }
+    });
+}
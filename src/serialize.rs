@@ -0,0 +1,139 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Turn serializable values into journal fields.
+
+use std::io::Write;
+
+use log::kv::{Error, Key, Visitor};
+use log::Record;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::fields::escape_field_name;
+
+/// Flatten the top-level fields of `value` into field names and values.
+///
+/// Write strings verbatim, and all other values, including nested structures,
+/// as JSON.  Fail if `value` doesn't serialize to a map or struct.
+pub fn flatten_fields<T: Serialize>(value: &T) -> std::io::Result<Vec<(String, String)>> {
+    match serde_json::to_value(value)? {
+        Value::Object(map) => Ok(map
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::String(s) => (key, s),
+                other => (key, other.to_string()),
+            })
+            .collect()),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "value does not serialize to a map or struct",
+        )),
+    }
+}
+
+/// Collect key-values as JSON strings under their escaped field names.
+struct CollectJsonFields(Map<String, Value>);
+
+impl<'kvs> Visitor<'kvs> for CollectJsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: log::kv::Value<'kvs>) -> Result<(), Error> {
+        let name = String::from_utf8_lossy(&escape_field_name(key.as_str())).into_owned();
+        self.0.insert(name, Value::String(value.to_string()));
+        Ok(())
+    }
+}
+
+/// Write `record` with `priority` as a single line of compact JSON to `writer`.
+///
+/// Write an object with the numeric `priority`, the `message`, and all
+/// key-values of `record` in `fields`, under their escaped field names.
+pub fn write_json_record<W: Write + ?Sized>(
+    writer: &mut W,
+    priority: u8,
+    record: &Record,
+) -> std::io::Result<()> {
+    let mut fields = CollectJsonFields(Map::new());
+    // Our visitor never fails
+    let _ = record.key_values().visit(&mut fields);
+    let object = serde_json::json!({
+        "priority": priority,
+        "message": record.args().to_string(),
+        "fields": fields.0,
+    });
+    serde_json::to_writer(&mut *writer, &object)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use similar_asserts::assert_eq;
+
+    #[derive(Serialize)]
+    struct Inner {
+        port: u16,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        name: &'static str,
+        count: u32,
+        enabled: bool,
+        inner: Inner,
+    }
+
+    #[test]
+    fn flatten_fields() {
+        let fields = super::flatten_fields(&Outer {
+            name: "foo",
+            count: 42,
+            enabled: true,
+            inner: Inner { port: 8080 },
+        })
+        .unwrap();
+        let mut fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                ("count", "42"),
+                ("enabled", "true"),
+                ("inner", r#"{"port":8080}"#),
+                ("name", "foo"),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_json_record() {
+        let kvs: &[(&str, log::kv::Value)] = &[("foo", log::kv::Value::from(42))];
+        let mut buffer = Vec::new();
+        super::write_json_record(
+            &mut buffer,
+            4,
+            &log::Record::builder()
+                .args(format_args!("Hello \"JSON\""))
+                .key_values(&kvs)
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{\"fields\":{\"FOO\":\"42\"},\"message\":\"Hello \\\"JSON\\\"\",\"priority\":4}\n"
+        );
+    }
+
+    #[test]
+    fn flatten_fields_no_map() {
+        let error = super::flatten_fields(&42).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -13,9 +13,10 @@
 
 use std::io::{Error, Result};
 use std::mem::{size_of, zeroed};
-use std::os::fd::BorrowedFd;
+use std::os::fd::{BorrowedFd, FromRawFd, OwnedFd};
+use std::os::linux::net::SocketAddrExt;
 use std::os::unix::ffi::OsStrExt;
-use std::os::unix::net::UnixDatagram;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
 use std::os::unix::prelude::{AsRawFd, RawFd};
 use std::path::Path;
 use std::ptr;
@@ -65,6 +66,7 @@ pub fn send_one_fd_to<P: AsRef<Path>>(
     let mut addr: sockaddr_un = unsafe { zeroed() };
     let path_bytes = path.as_ref().as_os_str().as_bytes();
     // path_bytes may have at most sun_path + 1 bytes, to account for the trailing NUL byte.
+    // Abstract names have no trailing NUL byte, but we keep the same limit for simplicity.
     if addr.sun_path.len() <= path_bytes.len() {
         return Err(Error::from_raw_os_error(ENAMETOOLONG));
     }
@@ -81,7 +83,12 @@ pub fn send_one_fd_to<P: AsRef<Path>>(
     let mut msg: msghdr = unsafe { zeroed() };
     // Set the target address.
     msg.msg_name = &mut addr as *mut _ as *mut c_void;
-    msg.msg_namelen = size_of::<sockaddr_un>() as socklen_t;
+    msg.msg_namelen = if is_abstract(path.as_ref()) {
+        // All bytes of sun_path are part of an abstract name, so pass its exact length
+        (size_of::<sa_family_t>() + path_bytes.len()) as socklen_t
+    } else {
+        size_of::<sockaddr_un>() as socklen_t
+    };
 
     // We send no data body with this message.
     msg.msg_iov = ptr::null_mut();
@@ -112,3 +119,131 @@ pub fn send_one_fd_to<P: AsRef<Path>>(
         Ok(result as usize)
     }
 }
+
+/// Whether `path` denotes an address in the abstract namespace, i.e. starts with a NUL byte.
+pub fn is_abstract(path: &Path) -> bool {
+    path.as_os_str().as_bytes().starts_with(b"\0")
+}
+
+/// Get the socket address for `path`.
+///
+/// If `path` starts with a NUL byte return an address for the rest of `path`
+/// in the abstract namespace.
+fn socket_addr(path: &Path) -> Result<SocketAddr> {
+    match path.as_os_str().as_bytes().strip_prefix(b"\0") {
+        Some(name) => SocketAddr::from_abstract_name(name),
+        None => SocketAddr::from_pathname(path),
+    }
+}
+
+/// Send `buf` over `socket` to `path`.
+///
+/// If `path` starts with a NUL byte send to the rest of `path` in the
+/// abstract namespace.
+pub fn send_to<P: AsRef<Path>>(socket: &UnixDatagram, buf: &[u8], path: P) -> Result<usize> {
+    if is_abstract(path.as_ref()) {
+        socket.send_to_addr(buf, &socket_addr(path.as_ref())?)
+    } else {
+        socket.send_to(buf, path)
+    }
+}
+
+/// Get the send buffer size of `socket`, i.e. `SO_SNDBUF`.
+pub fn send_buffer_size<S: AsRawFd>(socket: &S) -> Result<usize> {
+    let mut size: c_int = 0;
+    let mut len = size_of::<c_int>() as socklen_t;
+    // SAFETY: We pass a pointer to a properly sized and aligned c_int, and its size.
+    let result = unsafe {
+        getsockopt(
+            socket.as_raw_fd(),
+            SOL_SOCKET,
+            SO_SNDBUF,
+            &mut size as *mut c_int as *mut c_void,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(size as usize)
+    }
+}
+
+/// Enable or disable `SO_PASSCRED` on `socket`.
+pub fn set_passcred<S: AsRawFd>(socket: &S, enabled: bool) -> Result<()> {
+    let value = c_int::from(enabled);
+    // SAFETY: We pass a pointer to a properly sized and aligned c_int, and its size.
+    let result = unsafe {
+        setsockopt(
+            socket.as_raw_fd(),
+            SOL_SOCKET,
+            SO_PASSCRED,
+            &value as *const c_int as *const c_void,
+            size_of::<c_int>() as socklen_t,
+        )
+    };
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether `socket` has `SO_PASSCRED` enabled.
+pub fn passcred<S: AsRawFd>(socket: &S) -> Result<bool> {
+    let mut value: c_int = 0;
+    let mut len = size_of::<c_int>() as socklen_t;
+    // SAFETY: We pass a pointer to a properly sized and aligned c_int, and its size.
+    let result = unsafe {
+        getsockopt(
+            socket.as_raw_fd(),
+            SOL_SOCKET,
+            SO_PASSCRED,
+            &mut value as *mut c_int as *mut c_void,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(value != 0)
+    }
+}
+
+/// Whether `socket` is in non-blocking mode.
+pub fn is_nonblocking<S: AsRawFd>(socket: &S) -> Result<bool> {
+    // SAFETY: F_GETFL takes no further arguments and doesn't take ownership of the fd.
+    let flags = unsafe { fcntl(socket.as_raw_fd(), F_GETFL) };
+    if flags < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(flags & O_NONBLOCK != 0)
+    }
+}
+
+/// Create a `SOCK_SEQPACKET` socket connected to `path`.
+///
+/// If `path` starts with a NUL byte connect to the rest of `path` in the
+/// abstract namespace.
+///
+/// Return the socket as [`UnixDatagram`], which provides all operations we
+/// need for a connected seqpacket socket.
+pub fn connect_seqpacket<P: AsRef<Path>>(path: P) -> Result<UnixDatagram> {
+    // SAFETY: socket takes no pointers and returns a new fd or -1.
+    let fd = unsafe { socket(AF_UNIX, SOCK_SEQPACKET | SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: socket returned a new fd which nothing else owns.
+    let socket = UnixDatagram::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    socket.connect_addr(&socket_addr(path.as_ref())?)?;
+    Ok(socket)
+}
+
+/// Create a datagram socket bound to `address`.
+///
+/// If `address` starts with a NUL byte bind to the rest of `address` in the
+/// abstract namespace.
+pub fn bind_datagram<P: AsRef<Path>>(address: P) -> Result<UnixDatagram> {
+    UnixDatagram::bind_addr(&socket_addr(address.as_ref())?)
+}
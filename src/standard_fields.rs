@@ -0,0 +1,43 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The set of standard fields.
+
+bitflags::bitflags! {
+    /// A set of standard fields a [`crate::JournalLog`] writes.
+    ///
+    /// See [`crate::JournalLog`] for the meaning of these fields, and
+    /// [`crate::JournalLog::with_standard_fields`].
+    ///
+    /// The default set contains all fields.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct StandardFields: u16 {
+        /// The `PRIORITY` field.
+        const PRIORITY = 1 << 0;
+        /// The `MESSAGE` field.
+        const MESSAGE = 1 << 1;
+        /// The `SYSLOG_IDENTIFIER` field.
+        const SYSLOG_IDENTIFIER = 1 << 2;
+        /// The `SYSLOG_PID` field.
+        const SYSLOG_PID = 1 << 3;
+        /// The `CODE_FILE` field.
+        const CODE_FILE = 1 << 4;
+        /// The `CODE_LINE` field.
+        const CODE_LINE = 1 << 5;
+        /// The `CODE_MODULE` field.
+        const CODE_MODULE = 1 << 6;
+        /// The `TARGET` field.
+        const TARGET = 1 << 7;
+    }
+}
+
+impl Default for StandardFields {
+    fn default() -> Self {
+        Self::all()
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Write log records to standard error.
+
+use std::io::prelude::*;
+use std::io::IsTerminal;
+
+use log::{Level, Record};
+
+/// The ANSI color sequence for `level`.
+fn level_color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[1;31m",
+        Level::Warn => "\x1b[1;33m",
+        Level::Info => "\x1b[1;32m",
+        Level::Debug => "\x1b[1;34m",
+        Level::Trace => "\x1b[2m",
+    }
+}
+
+/// Write a concise line for `record` to `sink`.
+///
+/// If `colored` is `true` highlight the level with ANSI colors.
+pub fn write_record_line<W: Write>(
+    sink: &mut W,
+    record: &Record,
+    colored: bool,
+) -> std::io::Result<()> {
+    if colored {
+        writeln!(
+            sink,
+            "{}{:<5}\x1b[0m {}: {}",
+            level_color(record.level()),
+            record.level(),
+            record.target(),
+            record.args()
+        )
+    } else {
+        writeln!(
+            sink,
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        )
+    }
+}
+
+/// Write a concise line for `record` to standard error.
+///
+/// Use colors if standard error is a terminal.
+pub fn write_record(record: &Record) -> std::io::Result<()> {
+    let stderr = std::io::stderr();
+    let colored = stderr.is_terminal();
+    write_record_line(&mut stderr.lock(), record, colored)
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Record};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn write_record_line_plain() {
+        let mut buffer = Vec::new();
+        super::write_record_line(
+            &mut buffer,
+            &Record::builder()
+                .level(Level::Info)
+                .target("foo")
+                .args(format_args!("Hello {}", "World"))
+                .build(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "INFO  foo: Hello World\n"
+        );
+    }
+
+    #[test]
+    fn write_record_line_colored() {
+        let mut buffer = Vec::new();
+        super::write_record_line(
+            &mut buffer,
+            &Record::builder()
+                .level(Level::Error)
+                .target("foo")
+                .args(format_args!("oops"))
+                .build(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "\x1b[1;31mERROR\x1b[0m foo: oops\n"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Locks which ignore poisoning.
+//!
+//! A logger must keep logging after a panic elsewhere, and none of our locks
+//! guard invariants which a panic could break halfway: each critical section
+//! either replaces a value wholesale or updates plain counters and caches.
+//! The worst a panic can leave behind is a stale count or a partial line in a
+//! tee, so we take the data out of a poisoned lock and carry on.
+
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Lock `mutex`, ignoring poisoning.
+pub fn lock_ignoring_poison<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Lock `lock` for reading, ignoring poisoning.
+pub fn read_ignoring_poison<T: ?Sized>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Lock `lock` for writing, ignoring poisoning.
+pub fn write_ignoring_poison<T: ?Sized>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Wait on `condvar` with `guard`, ignoring poisoning.
+#[cfg(feature = "async")]
+pub fn wait_ignoring_poison<'a, T>(
+    condvar: &std::sync::Condvar,
+    guard: MutexGuard<'a, T>,
+) -> MutexGuard<'a, T> {
+    condvar
+        .wait(guard)
+        .unwrap_or_else(|error| error.into_inner())
+}
@@ -0,0 +1,63 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Send records to the classic syslog socket.
+
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::Record;
+
+use crate::timestamp;
+
+/// The path of the classic syslog socket.
+pub const SYSLOG_PATH: &str = "/dev/log";
+
+/// The syslog facility for user-level messages.
+pub const FACILITY_USER: u8 = 1;
+
+/// Whether `error` indicates that the journal socket is unavailable.
+pub fn journal_unavailable(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::NotFound | ErrorKind::ConnectionRefused
+    )
+}
+
+/// Format `record` as RFC 3164 syslog message.
+///
+/// `priority` is the journal priority of `record`, which equals the syslog
+/// severity.  Omit the timestamp if the local time is not available.
+pub fn format_record(priority: u8, identifier: &str, record: &Record, now: SystemTime) -> Vec<u8> {
+    let mut message = Vec::with_capacity(256);
+    write!(message, "<{}>", FACILITY_USER * 8 + priority).unwrap();
+    let seconds = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    if let Some(timestamp) = timestamp::syslog_timestamp(seconds as libc::time_t) {
+        write!(message, "{} ", timestamp).unwrap();
+    }
+    write!(
+        message,
+        "{}[{}]: {}",
+        identifier,
+        std::process::id(),
+        record.args()
+    )
+    .unwrap();
+    message
+}
+
+/// Send a syslog `message` to the socket at `path`.
+pub fn send(path: &Path, message: &[u8]) -> std::io::Result<()> {
+    UnixDatagram::unbound()?.send_to(message, path)?;
+    Ok(())
+}
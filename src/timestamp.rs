@@ -0,0 +1,128 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Timestamp fields.
+
+use std::io::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format `seconds` since the epoch as classic syslog timestamp in local time.
+///
+/// Return `None` if the local time is not available.
+pub fn syslog_timestamp(seconds: libc::time_t) -> Option<String> {
+    // SAFETY: tm is a plain C struct, for which all zeros is a valid value.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: We pass valid pointers to both arguments, and localtime_r is thread-safe.
+    let result = unsafe { libc::localtime_r(&seconds, &mut tm) };
+    if result.is_null() {
+        None
+    } else {
+        Some(format!(
+            "{} {:>2} {:02}:{:02}:{:02}",
+            MONTHS[tm.tm_mon as usize], tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec
+        ))
+    }
+}
+
+/// Format `when` as RFC 3339 timestamp in UTC, with microsecond precision.
+pub fn rfc3339(when: SystemTime) -> String {
+    let (seconds, micros) = match when.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => (since_epoch.as_secs() as i64, since_epoch.subsec_micros()),
+        Err(error) => {
+            let before_epoch = error.duration();
+            let seconds = -(before_epoch.as_secs() as i64);
+            match before_epoch.subsec_micros() {
+                0 => (seconds, 0),
+                micros => (seconds - 1, 1_000_000 - micros),
+            }
+        }
+    };
+    let seconds = seconds as libc::time_t;
+    // SAFETY: tm is a plain C struct, for which all zeros is a valid value.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: We pass valid pointers to both arguments, and gmtime_r is thread-safe.
+    // gmtime_r only fails if the year overflows an int, which SystemTime can't reach.
+    unsafe { libc::gmtime_r(&seconds, &mut tm) };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        tm.tm_year as i64 + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        micros
+    )
+}
+
+/// Put timestamp fields for `when` into `buffer`.
+///
+/// Write `SYSLOG_TIMESTAMP` as classic syslog timestamp in local time, and
+/// `SOURCE_REALTIME_TIMESTAMP` as microseconds since the epoch.
+///
+/// Fail if `when` is before the epoch.
+pub fn put_timestamp_fields(buffer: &mut Vec<u8>, when: SystemTime) -> std::io::Result<()> {
+    let since_epoch = when.duration_since(UNIX_EPOCH).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "timestamp before the epoch",
+        )
+    })?;
+    if let Some(timestamp) = syslog_timestamp(since_epoch.as_secs() as libc::time_t) {
+        writeln!(buffer, "SYSLOG_TIMESTAMP={}", timestamp)?;
+    }
+    writeln!(
+        buffer,
+        "SOURCE_REALTIME_TIMESTAMP={}",
+        since_epoch.as_micros()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn put_timestamp_fields() {
+        let mut buffer = Vec::new();
+        let when = UNIX_EPOCH + Duration::from_micros(1_696_161_600_123_456);
+        super::put_timestamp_fields(&mut buffer, when).unwrap();
+        let fields = String::from_utf8(buffer).unwrap();
+        let lines: Vec<_> = fields.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // The syslog timestamp depends on the local timezone
+        assert!(lines[0].starts_with("SYSLOG_TIMESTAMP="));
+        assert_eq!(lines[0].len(), "SYSLOG_TIMESTAMP=Oct  1 12:00:00".len());
+        assert_eq!(lines[1], "SOURCE_REALTIME_TIMESTAMP=1696161600123456");
+    }
+
+    #[test]
+    fn rfc3339() {
+        let when = UNIX_EPOCH + Duration::from_micros(1_696_161_600_123_456);
+        assert_eq!(super::rfc3339(when), "2023-10-01T12:00:00.123456Z");
+        assert_eq!(super::rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000000Z");
+        let before_epoch = UNIX_EPOCH - Duration::from_micros(1_500_000);
+        assert_eq!(super::rfc3339(before_epoch), "1969-12-31T23:59:58.500000Z");
+    }
+
+    #[test]
+    fn put_timestamp_fields_before_epoch() {
+        let mut buffer = Vec::new();
+        let when = UNIX_EPOCH - Duration::from_secs(1);
+        let error = super::put_timestamp_fields(&mut buffer, when).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(buffer.is_empty());
+    }
+}
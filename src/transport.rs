@@ -0,0 +1,39 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Transports for encoded journal entries.
+
+use crate::client::JournalClient;
+
+/// A transport which sends encoded journal entries somewhere.
+///
+/// See [`crate::JournalLog::with_transport`].
+pub trait JournalTransport: Send + Sync {
+    /// Send `data`, i.e. a single journal entry in the native journal protocol.
+    fn send(&self, data: &[u8]) -> std::io::Result<()>;
+
+    /// Send `data` to the journal namespace `namespace`.
+    ///
+    /// The logger calls this instead of [`Self::send`] for records which its
+    /// namespace router sends to a namespace (see
+    /// [`crate::JournalLog::with_namespace_router`]).  `namespace` is always
+    /// a valid namespace name.
+    ///
+    /// The default implementation ignores `namespace` and calls [`Self::send`].
+    fn send_to_namespace(&self, namespace: &str, data: &[u8]) -> std::io::Result<()> {
+        let _ = namespace;
+        self.send(data)
+    }
+}
+
+/// The default transport, which sends entries to the journal socket.
+impl JournalTransport for JournalClient {
+    fn send(&self, data: &[u8]) -> std::io::Result<()> {
+        self.send_payload(data).map(|_| ())
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Handle non-ASCII characters in keys of key-values.
+
+use std::borrow::Cow;
+
+/// What to do with non-ASCII characters in keys of key-values.
+///
+/// See [`crate::JournalLog::with_unicode_field_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UnicodeFieldPolicy {
+    /// Replace non-ASCII characters with underscores like all other invalid
+    /// characters, e.g. `Hallöchen` becomes `HALL_CHEN`.
+    #[default]
+    Replace,
+    /// Transliterate common Latin letters to ASCII before escaping, e.g.
+    /// `Hallöchen` becomes `HALLOECHEN`.
+    ///
+    /// Characters without transliteration still become underscores.
+    Transliterate,
+}
+
+impl UnicodeFieldPolicy {
+    /// Apply this policy to `key`, before escaping it.
+    pub(crate) fn apply<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self {
+            UnicodeFieldPolicy::Transliterate if !key.is_ascii() => Cow::Owned(transliterate(key)),
+            _ => Cow::Borrowed(key),
+        }
+    }
+}
+
+/// Fold `c` to ASCII on a best-effort basis.
+///
+/// Return `None` if we don't know an ASCII representation of `c`.
+fn fold_char(c: char) -> Option<&'static str> {
+    let folded = match c.to_lowercase().next().unwrap_or(c) {
+        'ä' | 'æ' => "ae",
+        'ö' | 'œ' => "oe",
+        'ü' => "ue",
+        'ß' => "ss",
+        'à' | 'á' | 'â' | 'ã' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ø' | 'ō' | 'ő' => "o",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// Transliterate all characters of `key` which we can fold to ASCII.
+fn transliterate(key: &str) -> String {
+    let mut transliterated = String::with_capacity(key.len());
+    for c in key.chars() {
+        match fold_char(c).filter(|_| !c.is_ascii()) {
+            Some(folded) => transliterated.push_str(folded),
+            None => transliterated.push(c),
+        }
+    }
+    transliterated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliterate() {
+        for (key, expected) in [
+            ("Hallöchen", "Halloechen"),
+            ("straße", "strasse"),
+            ("Ærøskøbing", "aeroskobing"),
+            ("crème brûlée", "creme brulee"),
+            ("ascii", "ascii"),
+            ("日本", "日本"),
+        ] {
+            assert_eq!(super::transliterate(key), expected, "{}", key);
+        }
+    }
+
+    #[test]
+    fn replace_keeps_key() {
+        assert!(matches!(
+            UnicodeFieldPolicy::Replace.apply("Hallöchen"),
+            Cow::Borrowed("Hallöchen")
+        ));
+    }
+}
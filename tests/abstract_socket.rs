@@ -0,0 +1,60 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test sending to a journal socket in the abstract namespace.
+
+#![deny(warnings, clippy::all)]
+
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::Duration;
+
+use log::{Level, Log, Record};
+
+use systemd_journal_logger::JournalLog;
+
+#[test]
+fn abstract_socket_path() {
+    let name = format!("systemd-journal-logger-abstract-{}", std::process::id());
+    let journal =
+        UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(name.as_bytes()).unwrap()).unwrap();
+    journal
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_path(format!("\0{}", name));
+    assert!(logger.validate().is_ok());
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("abstract_socket_path")
+            .args(format_args!("to the abstract namespace"))
+            .build(),
+    );
+
+    let mut buffer = vec![0; 4096];
+    let size = journal.recv(&mut buffer).unwrap();
+    let payload = String::from_utf8_lossy(&buffer[..size]);
+    assert!(payload.starts_with("PRIORITY=4\n"), "{payload:?}");
+    assert!(payload.contains("to the abstract namespace"), "{payload:?}");
+    assert_eq!(logger.diagnostics().sent, 1);
+
+    // Large entries go through a memfd, which needs to reach the abstract address as well
+    let large = "x".repeat(1024 * 1024);
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("abstract_socket_path")
+            .args(format_args!("{}", large))
+            .build(),
+    );
+    assert_eq!(journal.recv(&mut buffer).unwrap(), 0);
+    assert_eq!(logger.diagnostics().sent, 2);
+}
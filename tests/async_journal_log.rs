@@ -0,0 +1,147 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test logging from a background thread.
+
+#![deny(warnings, clippy::all)]
+#![cfg(feature = "async")]
+
+use std::os::unix::net::UnixDatagram;
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{AsyncJournalLog, JournalLog};
+
+mod journal;
+
+#[tokio::test]
+async fn flush_async() {
+    let logger = AsyncJournalLog::new(JournalLog::new().unwrap(), 16).unwrap();
+    for i in 0..3 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("flush_async")
+                .args(format_args!("queued record {}", i))
+                .build(),
+        );
+    }
+    logger.flush_async().await.unwrap();
+
+    let entries = journal::read_entries("flush_async", 3);
+    let messages: Vec<_> = entries.iter().map(|e| e["MESSAGE"].to_string()).collect();
+    assert_eq!(
+        messages,
+        vec!["queued record 0", "queued record 1", "queued record 2"]
+    );
+}
+
+#[test]
+fn sends_without_flush() {
+    let logger = AsyncJournalLog::new(JournalLog::new().unwrap(), 16).unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("sends_without_flush")
+            .args(format_args!("never flushed"))
+            .build(),
+    );
+
+    // read_one_entry waits for the entry, but we never flush the logger
+    let entry = journal::read_one_entry("sends_without_flush");
+    assert_eq!(entry["MESSAGE"], "never flushed");
+}
+
+#[tokio::test]
+async fn task_id_field() {
+    let logger = std::sync::Arc::new(JournalLog::new().unwrap().with_task_id_field(true));
+    let tasks: Vec<_> = (0..2)
+        .map(|task| {
+            let logger = logger.clone();
+            tokio::spawn(async move {
+                logger.log(
+                    &Record::builder()
+                        .level(Level::Info)
+                        .target("task_id_field")
+                        .args(format_args!("task {}", task))
+                        .build(),
+                );
+                tokio::task::id().to_string()
+            })
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for task in tasks {
+        ids.push(task.await.unwrap());
+    }
+    // Outside of a spawned task the logger omits the field
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("task_id_field")
+            .args(format_args!("no task"))
+            .build(),
+    );
+
+    let entries = journal::read_entries("task_id_field", 3);
+    let task_ids: Vec<_> = entries
+        .iter()
+        .map(|entry| entry.get("TASK_ID").map(|id| id.to_string()))
+        .collect();
+    assert_ne!(ids[0], ids[1]);
+    assert_eq!(
+        task_ids,
+        vec![Some(ids[0].clone()), Some(ids[1].clone()), None]
+    );
+}
+
+#[tokio::test]
+async fn routes_and_falls_back_like_journal_log() {
+    let directory = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-async-routes-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("journal.errors")).unwrap();
+    let errors = UnixDatagram::bind(directory.join("journal.errors").join("socket")).unwrap();
+    let syslog = UnixDatagram::bind(directory.join("syslog")).unwrap();
+
+    // Nobody listens on the default journal socket, so the logger falls back to syslog
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("async".to_string())
+        .with_socket_path(directory.join("journal").join("socket"))
+        .with_namespace_router(|level| (level == Level::Error).then(|| "errors".to_string()))
+        .with_syslog_fallback(true)
+        .with_syslog_socket_path(directory.join("syslog"));
+    let logger = AsyncJournalLog::new(logger, 16).unwrap();
+    for (level, message) in [(Level::Error, "to errors"), (Level::Info, "to syslog")] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("routes_and_falls_back_like_journal_log")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+    logger.flush_async().await.unwrap();
+
+    let mut buffer = vec![0; 4096];
+    let size = errors.recv(&mut buffer).unwrap();
+    let entry = String::from_utf8_lossy(&buffer[..size]).into_owned();
+    assert!(entry.contains("to errors"), "{entry:?}");
+    let size = syslog.recv(&mut buffer).unwrap();
+    let message = String::from_utf8_lossy(&buffer[..size]).into_owned();
+    assert!(
+        message.ends_with(&format!(" async[{}]: to syslog", std::process::id())),
+        "{message:?}"
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
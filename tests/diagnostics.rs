@@ -0,0 +1,125 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![deny(warnings, clippy::all)]
+
+use std::path::Path;
+
+use log::{Level, LevelFilter, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{JournalLog, SocketType, StandardFields};
+
+mod journal;
+
+#[test]
+fn diagnostics() {
+    let logger = JournalLog::new().unwrap();
+    let diagnostics = logger.diagnostics();
+    assert_eq!(
+        diagnostics.socket_path,
+        Path::new("/run/systemd/journal/socket")
+    );
+    assert!(diagnostics.send_buffer_size.unwrap() > 0);
+    assert_eq!(diagnostics.nonblocking, Some(false));
+    assert_eq!(diagnostics.sent, 0);
+    assert_eq!(diagnostics.dropped, 0);
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("diagnostics")
+            .args(format_args!("counted"))
+            .build(),
+    );
+    let diagnostics = logger.diagnostics();
+    assert_eq!(diagnostics.sent, 1);
+    assert_eq!(diagnostics.dropped, 0);
+}
+
+#[test]
+fn large_payload_threshold() {
+    let logger = JournalLog::new().unwrap();
+    let diagnostics = logger.diagnostics();
+    let threshold = diagnostics.large_payload_threshold;
+    assert_eq!(threshold, diagnostics.send_buffer_size.unwrap() / 4 * 3);
+
+    // Log messages just below and just above the threshold; both must arrive,
+    // the first as datagram, the second through a memfd.
+    for (target, size) in [
+        ("large_payload_threshold_below", threshold - 512),
+        ("large_payload_threshold_above", threshold + 512),
+    ] {
+        let message = "x".repeat(size);
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+        let entry = journal::read_one_entry(target);
+        assert_eq!(entry["MESSAGE"].as_text(), message);
+    }
+    assert_eq!(logger.diagnostics().sent, 2);
+}
+
+#[test]
+fn max_level_off_closes_socket() {
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("max_level_off_closes_socket")
+        .args(format_args!("after reenabling"))
+        .build();
+
+    let logger = JournalLog::new().unwrap().with_max_level(LevelFilter::Off);
+    assert!(!logger.enabled(record.metadata()));
+    assert!(!logger.diagnostics().connected);
+    logger.log(&record);
+    assert_eq!(logger.diagnostics().sent, 0);
+    assert!(!logger.diagnostics().connected);
+
+    let logger = logger.with_max_level(LevelFilter::Info);
+    assert!(logger.enabled(record.metadata()));
+    assert!(!logger.would_log(Level::Debug, "max_level_off_closes_socket"));
+    logger.log(&record);
+    assert!(logger.diagnostics().connected);
+    assert_eq!(logger.diagnostics().sent, 1);
+    let entry = journal::read_one_entry("max_level_off_closes_socket");
+    assert_eq!(entry["MESSAGE"], "after reenabling");
+}
+
+#[test]
+fn config() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("config".to_string())
+        .with_extra_fields(vec![("foo", "bar"), ("BAZ", "qux")])
+        .with_max_level(LevelFilter::Warn)
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .with_logger_field_name("LOGGER".to_string())
+        .unwrap()
+        .with_socket_path("/tmp/config.socket");
+    let config = logger.config();
+    assert_eq!(config.syslog_identifier, "config");
+    assert_eq!(
+        config.extra_fields,
+        vec![
+            ("FOO".to_string(), b"bar".to_vec()),
+            ("BAZ".to_string(), b"qux".to_vec())
+        ]
+    );
+    assert_eq!(config.max_level, LevelFilter::Warn);
+    assert_eq!(
+        config.standard_fields,
+        StandardFields::PRIORITY | StandardFields::MESSAGE
+    );
+    assert_eq!(config.target_field_name, "LOGGER");
+    assert_eq!(config.socket_path, Path::new("/tmp/config.socket"));
+    assert_eq!(config.socket_type, SocketType::Datagram);
+}
@@ -38,7 +38,7 @@ impl FieldValue {
         match self {
             FieldValue::Text(v) => Cow::Borrowed(v.as_str()),
             FieldValue::Binary(binary) => String::from_utf8_lossy(binary),
-            FieldValue::Array(v) => Cow::Borrowed(v.get(0).map_or("", |s| s.as_str())),
+            FieldValue::Array(v) => Cow::Borrowed(v.first().map_or("", |s| s.as_str())),
         }
     }
 }
@@ -148,3 +148,20 @@ pub fn read_one_entry(target: &str) -> HashMap<String, FieldValue> {
     })
     .unwrap()
 }
+
+pub fn read_entries(target: &str, count: usize) -> Vec<HashMap<String, FieldValue>> {
+    retry::retry(Fixed::from_millis(100).take(30), || {
+        let entries = read_current_process(target);
+        if entries.len() == count {
+            Ok(entries)
+        } else {
+            Err(format!(
+                "Expected {} entries in journal for target {}, got {}",
+                count,
+                target,
+                entries.len()
+            ))
+        }
+    })
+    .unwrap()
+}
@@ -0,0 +1,1052 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Golden tests for the encoded journal payload.
+
+#![deny(warnings, clippy::all)]
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use log::kv::{self, Key, Source, Value, VisitSource};
+use log::{Level, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{
+    CollisionPolicy, EmptyFieldPolicy, Error, JournalLog, Priority, StandardFields,
+    UnicodeFieldPolicy,
+};
+
+#[test]
+fn standard_fields_in_order() {
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_syslog_identifier("golden".to_string())
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("standard_fields_in_order")
+                .module_path(Some("golden::module"))
+                .file(Some("src/golden.rs"))
+                .line(Some(42))
+                .args(format_args!("Hello {}", "World"))
+                .build(),
+        );
+
+    let expected = format!(
+        "PRIORITY=4
+MESSAGE
+\x0b\0\0\0\0\0\0\0Hello World
+SYSLOG_IDENTIFIER=golden
+SYSLOG_PID={}
+CODE_FILE=src/golden.rs
+CODE_LINE=42
+CODE_MODULE=golden::module
+TARGET=standard_fields_in_order
+",
+        std::process::id()
+    );
+    assert_eq!(String::from_utf8(payload).unwrap(), expected);
+}
+
+#[test]
+fn extra_fields_before_sorted_key_values() {
+    let kvs: &[(&str, Value)] = &[
+        ("zzz", Value::from("last")),
+        ("foo", Value::from(42)),
+        ("aaa", Value::from("first")),
+    ];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_extra_fields(vec![("SPAM", "eggs"), ("BAR", "baz")])
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Error)
+                .target("sorted")
+                .args(format_args!("Hi"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+    let expected = format!(
+        "PRIORITY=3
+MESSAGE
+\x02\0\0\0\0\0\0\0Hi
+SYSLOG_PID={}
+TARGET=sorted
+SPAM=eggs
+BAR=baz
+AAA
+\x05\0\0\0\0\0\0\0first
+FOO
+\x02\0\0\0\0\0\0\042
+ZZZ
+\x04\0\0\0\0\0\0\0last
+",
+        std::process::id()
+    );
+    assert_eq!(String::from_utf8(payload).unwrap(), expected);
+}
+
+#[test]
+fn truncated_message_is_valid_utf8() {
+    let payload = JournalLog::empty()
+        .unwrap()
+        // Every ö has two bytes, so seven bytes would split the fourth ö
+        .with_max_message_len(7)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("truncated")
+                .args(format_args!("{}", "ö".repeat(10)))
+                .build(),
+        );
+
+    let expected = format!(
+        "PRIORITY=5
+MESSAGE
+\x06\0\0\0\0\0\0\0ööö
+SYSLOG_PID={}
+TARGET=truncated
+",
+        std::process::id()
+    );
+    assert_eq!(String::from_utf8(payload).unwrap(), expected);
+}
+
+#[test]
+fn numeric_booleans_toggle() {
+    let kvs: &[(&str, Value)] = &[("spam_with_eggs", Value::from(false))];
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("numeric_booleans_toggle")
+        .args(format_args!("Hi"))
+        .key_values(&kvs)
+        .build();
+    let logger = JournalLog::empty().unwrap();
+
+    let textual = String::from_utf8(logger.journal_encode(&record)).unwrap();
+    assert!(textual.ends_with("SPAM_WITH_EGGS\n\x05\0\0\0\0\0\0\0false\n"));
+
+    let logger = logger.with_numeric_booleans(true);
+    let numeric = String::from_utf8(logger.journal_encode(&record)).unwrap();
+    assert!(numeric.ends_with("SPAM_WITH_EGGS=0\n"));
+}
+
+#[test]
+fn only_priority_and_message() {
+    let payload = JournalLog::new()
+        .unwrap()
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Debug)
+                .target("only_priority_and_message")
+                .module_path(Some("golden::module"))
+                .file(Some("src/golden.rs"))
+                .line(Some(42))
+                .args(format_args!("Lean"))
+                .build(),
+        );
+
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "PRIORITY=6\nMESSAGE\n\x04\0\0\0\0\0\0\0Lean\n"
+    );
+}
+
+#[test]
+fn sample_fields_only_when_sampling() {
+    let record = Record::builder()
+        .level(Level::Debug)
+        .target("sample_fields_only_when_sampling")
+        .args(format_args!("Sampled"))
+        .build();
+    for keep_every in [0, 1] {
+        let logger = JournalLog::empty()
+            .unwrap()
+            .with_sampling(Level::Debug, keep_every);
+        let payload = String::from_utf8(logger.journal_encode(&record)).unwrap();
+        assert!(!payload.contains("SAMPLED="), "{}", payload);
+        assert!(!payload.contains("SAMPLE_RATE="), "{}", payload);
+    }
+
+    let logger = JournalLog::empty().unwrap().with_sampling(Level::Debug, 2);
+    let payload = String::from_utf8(logger.journal_encode(&record)).unwrap();
+    assert!(
+        payload.ends_with("SAMPLED=1\nSAMPLE_RATE=2\n"),
+        "{}",
+        payload
+    );
+}
+
+/// Get standard fields as pairs of strings, for easier comparison.
+fn standard_field_strings(logger: &JournalLog, record: &Record) -> Vec<(String, String)> {
+    logger
+        .standard_fields(record)
+        .into_iter()
+        .map(|(name, value)| (name, String::from_utf8(value).unwrap()))
+        .collect()
+}
+
+#[test]
+fn standard_fields_pairs() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_syslog_identifier("golden".to_string());
+    let pairs = standard_field_strings(
+        &logger,
+        &Record::builder()
+            .level(Level::Error)
+            .target("standard_fields_pairs")
+            .module_path(Some("golden::module"))
+            .file(Some("src/golden.rs"))
+            .line(Some(42))
+            .args(format_args!("Hello {}", "World"))
+            .build(),
+    );
+
+    assert_eq!(
+        pairs,
+        vec![
+            ("PRIORITY".to_string(), "3".to_string()),
+            ("MESSAGE".to_string(), "Hello World".to_string()),
+            ("SYSLOG_IDENTIFIER".to_string(), "golden".to_string()),
+            ("SYSLOG_PID".to_string(), std::process::id().to_string()),
+            ("CODE_FILE".to_string(), "src/golden.rs".to_string()),
+            ("CODE_LINE".to_string(), "42".to_string()),
+            ("CODE_MODULE".to_string(), "golden::module".to_string()),
+            ("TARGET".to_string(), "standard_fields_pairs".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn standard_fields_pairs_selected_and_truncated() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_max_message_len(5)
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE);
+    let pairs = standard_field_strings(
+        &logger,
+        &Record::builder()
+            .level(Level::Debug)
+            .target("standard_fields_pairs_selected_and_truncated")
+            .args(format_args!("Hello World"))
+            .build(),
+    );
+
+    assert_eq!(
+        pairs,
+        vec![
+            ("PRIORITY".to_string(), "6".to_string()),
+            ("MESSAGE".to_string(), "Hello".to_string())
+        ]
+    );
+}
+
+#[test]
+fn logger_field_name() {
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::TARGET)
+        .with_logger_field_name("LOGGER".to_string())
+        .unwrap()
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("logger_field_name")
+                .args(format_args!("renamed target"))
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "LOGGER=logger_field_name\n"
+    );
+}
+
+#[test]
+fn logger_field_name_invalid() {
+    let result = JournalLog::empty()
+        .unwrap()
+        .with_logger_field_name("logger".to_string());
+    assert!(
+        matches!(result, Err(Error::InvalidFieldName(ref name)) if name == "logger"),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn module_strip_crate() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::CODE_MODULE)
+        .with_module_strip_crate(true);
+    let module = |module_path: &str| {
+        let payload = logger.journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("module_strip_crate")
+                .module_path(Some(module_path))
+                .args(format_args!("stripped module"))
+                .build(),
+        );
+        String::from_utf8(payload).unwrap()
+    };
+    assert_eq!(module("my_app::net::http"), "CODE_MODULE=net::http\n");
+    assert_eq!(module("my_app::net"), "CODE_MODULE=net\n");
+    assert_eq!(module("my_app"), "");
+}
+
+/// Remove ANSI SGR escape sequences like `\x1b[1;31m` from `message`.
+fn strip_ansi(message: &str) -> String {
+    let mut stripped = String::with_capacity(message.len());
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[test]
+fn message_transform() {
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::MESSAGE)
+        .with_trim_trailing_newline(true)
+        .with_message_transform(|message| strip_ansi(message) + "\n")
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Error)
+                .target("message_transform")
+                .args(format_args!("\x1b[1;31merror\x1b[0m: {}", "disk full"))
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "MESSAGE\n\x10\0\0\0\0\0\0\0error: disk full\n"
+    );
+}
+
+#[test]
+fn max_entry_bytes() {
+    let kvs: &[(&str, Value)] = &[
+        ("e", Value::from("x")),
+        ("d", Value::from("x")),
+        ("c", Value::from("x")),
+        ("b", Value::from("x")),
+        ("a", Value::from("x")),
+    ];
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .with_max_entry_bytes(55);
+    let record = Record::builder()
+        .level(Level::Error)
+        .target("max_entry_bytes")
+        .args(format_args!("Hi"))
+        .key_values(&kvs)
+        .build();
+
+    // 30 bytes for standard fields, and 12 bytes for every key-value
+    assert_eq!(
+        String::from_utf8(logger.journal_encode(&record)).unwrap(),
+        "PRIORITY=3\nMESSAGE\n\x02\0\0\0\0\0\0\0Hi\nA\n\x01\0\0\0\0\0\0\0x\nB\n\x01\0\0\0\0\0\0\0x\nBUDGET_EXCEEDED=1\n"
+    );
+    // Always write the message, even if it exceeds the budget
+    let logger = logger.with_max_entry_bytes(10);
+    assert_eq!(
+        String::from_utf8(logger.journal_encode(&record)).unwrap(),
+        "PRIORITY=3\nMESSAGE\n\x02\0\0\0\0\0\0\0Hi\nBUDGET_EXCEEDED=1\n"
+    );
+}
+
+/// A value which fails to format.
+struct Unformattable;
+
+impl std::fmt::Display for Unformattable {
+    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Err(std::fmt::Error)
+    }
+}
+
+/// A source which fails after its first key-value.
+struct FailingSource;
+
+impl Source for FailingSource {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        visitor.visit_pair(Key::from("before"), Value::from("ok"))?;
+        Err(kv::Error::msg("broken source"))
+    }
+}
+
+#[test]
+fn kv_errors() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let logger = {
+        let errors = errors.clone();
+        JournalLog::empty()
+            .unwrap()
+            .with_standard_fields(StandardFields::PRIORITY)
+            .on_error(move |error| errors.lock().unwrap().push(error.to_string()))
+    };
+    let kvs: &[(&str, Value)] = &[
+        ("broken", Value::from_display(&Unformattable)),
+        ("fine", Value::from("yes")),
+    ];
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("kv_errors")
+        .key_values(&kvs)
+        .build();
+    let payload = logger.journal_encode(&record);
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "PRIORITY=5\nFINE\n\x03\0\0\0\0\0\0\0yes\nKV_ERRORS=1\n"
+    );
+
+    let payload = logger.journal_encode(
+        &Record::builder()
+            .level(Level::Info)
+            .target("kv_errors")
+            .key_values(&FailingSource)
+            .build(),
+    );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "PRIORITY=5\nBEFORE\n\x02\0\0\0\0\0\0\0ok\nKV_ERRORS=1\n"
+    );
+    // Encoding alone doesn't report errors
+    assert!(errors.lock().unwrap().is_empty());
+
+    // Sending reports errors once per record
+    logger.journal_send(&record).unwrap();
+    assert_eq!(
+        *errors.lock().unwrap(),
+        vec!["key-values failed to serialize (KV_ERRORS=1)"]
+    );
+}
+
+#[test]
+fn field_redactor() {
+    let kvs: &[(&str, Value)] = &[
+        ("token", Value::from("secret")),
+        ("user", Value::from("alice")),
+    ];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .with_field_redactor(|name, value| match name {
+            "TOKEN" => Some(b"****".to_vec()),
+            "MESSAGE" => Some(value.to_ascii_uppercase()),
+            "PRIORITY" => None,
+            _ => Some(value.to_vec()),
+        })
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("field_redactor")
+                .args(format_args!("login"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "MESSAGE\n\x05\0\0\0\0\0\0\0LOGIN\nTOKEN\n\x04\0\0\0\0\0\0\0****\nUSER\n\x05\0\0\0\0\0\0\0alice\n"
+    );
+}
+
+#[test]
+fn identifier_map() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_syslog_identifier("default".to_string())
+        .with_standard_fields(StandardFields::SYSLOG_IDENTIFIER)
+        .with_identifier_map(vec![
+            ("app".to_string(), "app".to_string()),
+            ("app::db".to_string(), "app-db".to_string()),
+            ("worker".to_string(), "worker".to_string()),
+        ]);
+    let identifier = |target: &str| {
+        let payload = logger.journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target(target)
+                .args(format_args!("mapped identifier"))
+                .build(),
+        );
+        String::from_utf8(payload).unwrap()
+    };
+    assert_eq!(identifier("app::db::pool"), "SYSLOG_IDENTIFIER=app-db\n");
+    assert_eq!(identifier("app::http"), "SYSLOG_IDENTIFIER=app\n");
+    assert_eq!(identifier("worker"), "SYSLOG_IDENTIFIER=worker\n");
+    assert_eq!(identifier("other"), "SYSLOG_IDENTIFIER=default\n");
+}
+
+#[test]
+fn clock_timestamps() {
+    let now = UNIX_EPOCH + Duration::from_micros(1_696_161_600_123_456);
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::empty())
+        .with_clock(Arc::new(move || now))
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("clock_timestamps")
+                .args(format_args!("at a fixed time"))
+                .build(),
+        );
+
+    let payload = String::from_utf8(payload).unwrap();
+    let lines: Vec<_> = payload.lines().collect();
+    assert_eq!(lines.len(), 2);
+    // The syslog timestamp depends on the local timezone
+    assert!(lines[0].starts_with("SYSLOG_TIMESTAMP="));
+    assert_eq!(lines[1], "SOURCE_REALTIME_TIMESTAMP=1696161600123456");
+}
+
+#[test]
+fn extra_fields_of_any_string_type() {
+    let encode = |logger: JournalLog| {
+        logger.journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("extra_fields_of_any_string_type")
+                .args(format_args!("with extra fields"))
+                .build(),
+        )
+    };
+    let static_strs = encode(
+        JournalLog::empty()
+            .unwrap()
+            .with_extra_fields(vec![("FOO", "bar"), ("SPAM", "eggs")]),
+    );
+    let strings = encode(JournalLog::empty().unwrap().with_extra_fields(vec![
+        ("FOO".to_string(), "bar".to_string()),
+        ("SPAM".to_string(), "eggs".to_string()),
+    ]));
+    let cows = encode(JournalLog::empty().unwrap().with_extra_fields(vec![
+        (Cow::Borrowed("FOO"), Cow::Borrowed(b"bar".as_slice())),
+        (Cow::Owned("SPAM".to_string()), Cow::Owned(b"eggs".to_vec())),
+    ]));
+
+    assert!(String::from_utf8_lossy(&static_strs).ends_with("FOO=bar\nSPAM=eggs\n"));
+    assert_eq!(strings, static_strs);
+    assert_eq!(cows, static_strs);
+}
+
+/// Encode a record with colliding keys with the given collision `policy`.
+fn encode_colliding_keys(policy: CollisionPolicy) -> String {
+    let kvs: &[(&str, Value)] = &[
+        ("Foo", Value::from("a")),
+        ("foo", Value::from("b")),
+        ("bar", Value::from("c")),
+    ];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::empty())
+        .with_collision_policy(policy)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("colliding_keys")
+                .args(format_args!("colliding keys"))
+                .key_values(&kvs)
+                .build(),
+        );
+    String::from_utf8(payload).unwrap()
+}
+
+#[test]
+fn collision_policy_keep() {
+    assert_eq!(
+        encode_colliding_keys(CollisionPolicy::Keep),
+        "BAR\n\x01\0\0\0\0\0\0\0c\nFOO\n\x01\0\0\0\0\0\0\0a\nFOO\n\x01\0\0\0\0\0\0\0b\n"
+    );
+}
+
+#[test]
+fn collision_policy_overwrite() {
+    assert_eq!(
+        encode_colliding_keys(CollisionPolicy::Overwrite),
+        "BAR\n\x01\0\0\0\0\0\0\0c\nFOO\n\x01\0\0\0\0\0\0\0b\n"
+    );
+}
+
+#[test]
+fn collision_policy_suffix() {
+    assert_eq!(
+        encode_colliding_keys(CollisionPolicy::Suffix),
+        "BAR\n\x01\0\0\0\0\0\0\0c\nFOO\n\x01\0\0\0\0\0\0\0a\nFOO_2\n\x01\0\0\0\0\0\0\0b\n"
+    );
+}
+
+#[test]
+fn collision_policy_error() {
+    let kvs: &[(&str, Value)] = &[("Foo", Value::from("a")), ("foo", Value::from("b"))];
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_collision_policy(CollisionPolicy::Error);
+    let error = logger
+        .journal_send(
+            &Record::builder()
+                .level(Level::Info)
+                .target("collision_policy_error")
+                .args(format_args!("colliding keys"))
+                .key_values(&kvs)
+                .build(),
+        )
+        .unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::FieldCollision { ref first, ref second, ref field }
+                if first == "Foo" && second == "foo" && field == "FOO"
+        ),
+        "{error:?}"
+    );
+    assert_eq!(
+        error.to_string(),
+        "keys Foo and foo both escape to field FOO"
+    );
+    assert_eq!(logger.diagnostics().sent, 0);
+}
+
+fn encode_priority(logger: &JournalLog, level: Level, kvs: &[(&str, Value)]) -> String {
+    let payload = logger.journal_encode(
+        &Record::builder()
+            .level(level)
+            .target("priority")
+            .args(format_args!("priority"))
+            .key_values(&kvs)
+            .build(),
+    );
+    let payload = String::from_utf8(payload).unwrap();
+    payload.lines().next().unwrap().to_string()
+}
+
+#[test]
+fn priority_mapping() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_priority_mapping(|level| match level {
+            Level::Error => Priority::Critical,
+            level => Priority::from(level),
+        });
+    assert_eq!(encode_priority(&logger, Level::Error, &[]), "PRIORITY=2");
+    assert_eq!(encode_priority(&logger, Level::Info, &[]), "PRIORITY=5");
+    // An explicit severity still takes precedence
+    assert_eq!(
+        encode_priority(&logger, Level::Error, &[("severity", Value::from("alert"))]),
+        "PRIORITY=1"
+    );
+}
+
+#[test]
+fn target_priority_ceiling() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_target_priority_ceiling(vec![
+            ("chatty".to_string(), 4),
+            ("chatty::loud".to_string(), 3),
+        ]);
+    let encode = |target: &str, level: Level| {
+        let payload = logger.journal_encode(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("priority"))
+                .build(),
+        );
+        String::from_utf8(payload)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    // Errors of chatty targets are demoted to warnings
+    assert_eq!(encode("chatty::db", Level::Error), "PRIORITY=4");
+    assert_eq!(encode("chatty::db", Level::Info), "PRIORITY=5");
+    // The longest prefix wins
+    assert_eq!(encode("chatty::loud", Level::Error), "PRIORITY=3");
+    assert_eq!(encode("quiet", Level::Error), "PRIORITY=3");
+}
+
+#[test]
+fn priority_floor() {
+    let logger = JournalLog::empty().unwrap().with_priority_floor(5);
+    assert_eq!(encode_priority(&logger, Level::Debug, &[]), "PRIORITY=5");
+    assert_eq!(encode_priority(&logger, Level::Trace, &[]), "PRIORITY=5");
+    assert_eq!(encode_priority(&logger, Level::Warn, &[]), "PRIORITY=4");
+    // The floor also applies to mapped priorities and explicit severities
+    let logger = logger.with_priority_mapping(|_| Priority::Debug);
+    assert_eq!(encode_priority(&logger, Level::Error, &[]), "PRIORITY=5");
+    assert_eq!(
+        encode_priority(&logger, Level::Error, &[("severity", Value::from("crit"))]),
+        "PRIORITY=2"
+    );
+    assert_eq!(
+        encode_priority(&logger, Level::Error, &[("severity", Value::from("info"))]),
+        "PRIORITY=5"
+    );
+}
+
+fn encode_empty_field(policy: EmptyFieldPolicy) -> String {
+    let kvs: &[(&str, Value)] = &[("note", Value::from("")), ("foo", Value::from("bar"))];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::empty())
+        .with_empty_field_policy(policy)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("empty_field")
+                .args(format_args!("empty field"))
+                .key_values(&kvs)
+                .build(),
+        );
+    String::from_utf8(payload).unwrap()
+}
+
+#[test]
+fn empty_field_policy_keep() {
+    assert_eq!(
+        encode_empty_field(EmptyFieldPolicy::Keep),
+        "FOO\n\x03\0\0\0\0\0\0\0bar\nNOTE\n\0\0\0\0\0\0\0\0\n"
+    );
+}
+
+#[test]
+fn empty_field_policy_drop() {
+    assert_eq!(
+        encode_empty_field(EmptyFieldPolicy::Drop),
+        "FOO\n\x03\0\0\0\0\0\0\0bar\n"
+    );
+}
+
+fn encode_unicode_field(policy: UnicodeFieldPolicy) -> String {
+    let kvs: &[(&str, Value)] = &[("Hallöchen", Value::from("Welt"))];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::empty())
+        .with_unicode_field_policy(policy)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("unicode_field")
+                .args(format_args!("unicode field"))
+                .key_values(&kvs)
+                .build(),
+        );
+    String::from_utf8(payload).unwrap()
+}
+
+#[test]
+fn unicode_field_policy_replace() {
+    assert_eq!(
+        encode_unicode_field(UnicodeFieldPolicy::Replace),
+        "HALL_CHEN\n\x04\0\0\0\0\0\0\0Welt\n"
+    );
+}
+
+#[test]
+fn unicode_field_policy_transliterate() {
+    assert_eq!(
+        encode_unicode_field(UnicodeFieldPolicy::Transliterate),
+        "HALLOECHEN\n\x04\0\0\0\0\0\0\0Welt\n"
+    );
+}
+
+#[test]
+fn reconfigure_concurrently() {
+    let logger = Arc::new(
+        JournalLog::empty()
+            .unwrap()
+            .with_standard_fields(StandardFields::SYSLOG_IDENTIFIER)
+            .with_syslog_identifier("a".to_string())
+            .add_extra_field("GENERATION", "a"),
+    );
+    let configs: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|generation| {
+            let mut config = logger.config();
+            config.syslog_identifier = generation.to_string();
+            config.extra_fields = vec![("GENERATION".to_string(), generation.as_bytes().to_vec())];
+            config
+        })
+        .collect();
+
+    let reconfigure = {
+        let logger = logger.clone();
+        std::thread::spawn(move || {
+            for config in configs.iter().cycle().take(1000) {
+                logger.reconfigure(config.clone());
+            }
+        })
+    };
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("reconfigure_concurrently")
+        .args(format_args!("reconfigured"))
+        .build();
+    for _ in 0..1000 {
+        let payload = String::from_utf8(logger.journal_encode(&record)).unwrap();
+        assert!(
+            payload == "SYSLOG_IDENTIFIER=a\nGENERATION=a\n"
+                || payload == "SYSLOG_IDENTIFIER=b\nGENERATION=b\n",
+            "{:?}",
+            payload
+        );
+    }
+    reconfigure.join().unwrap();
+}
+
+#[derive(Debug)]
+struct DiskFull {
+    device: &'static str,
+}
+
+impl std::fmt::Display for DiskFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "disk {} full", self.device)
+    }
+}
+
+impl std::error::Error for DiskFull {}
+
+#[test]
+fn error_values() {
+    let error = DiskFull { device: "sda" };
+    let kvs: &[(&str, Value)] = &[("error", Value::from_dyn_error(&error))];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::empty())
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Error)
+                .target("error_values")
+                .args(format_args!("write failed"))
+                .key_values(&kvs)
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "ERROR\n\x0d\0\0\0\0\0\0\0disk sda full\nERROR_DEBUG\n\x1a\0\0\0\0\0\0\0DiskFull { device: \"sda\" }\n"
+    );
+}
+
+#[test]
+fn identifier_with_control_byte() {
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::SYSLOG_IDENTIFIER)
+        .with_syslog_identifier("bell\x07".to_string())
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("identifier_with_control_byte")
+                .args(format_args!("ding"))
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "SYSLOG_IDENTIFIER\n\x05\0\0\0\0\0\0\0bell\x07\n"
+    );
+}
+
+#[test]
+fn ring_buffer() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .with_ring_buffer(3);
+    for i in 0..5 {
+        logger
+            .journal_send(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("ring_buffer")
+                    .args(format_args!("record {}", i))
+                    .build(),
+            )
+            .unwrap();
+    }
+
+    let recent = logger.recent();
+    let messages: Vec<_> = recent
+        .iter()
+        .map(|entry| String::from_utf8(entry.field("MESSAGE").unwrap()).unwrap())
+        .collect();
+    assert_eq!(messages, vec!["record 2", "record 3", "record 4"]);
+    assert!(recent
+        .iter()
+        .all(|entry| entry.level == Level::Info && entry.target == "ring_buffer"));
+    assert_eq!(
+        recent[0].fields(),
+        vec![
+            ("PRIORITY".to_string(), b"5".to_vec()),
+            ("MESSAGE".to_string(), b"record 2".to_vec())
+        ]
+    );
+    assert!(JournalLog::empty().unwrap().recent().is_empty());
+}
+
+#[test]
+fn estimate_size() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let logger = {
+        let errors = errors.clone();
+        JournalLog::empty()
+            .unwrap()
+            .with_syslog_identifier("estimate".to_string())
+            .with_extra_fields(vec![("SERVICE", "estimate")])
+            .with_sequence_field(true)
+            .with_thread_id_field(true)
+            .on_error(move |error| errors.lock().unwrap().push(error.to_string()))
+    };
+    let kvs: &[(&str, Value)] = &[
+        ("user", Value::from("alice")),
+        ("broken", Value::from_display(&Unformattable)),
+    ];
+    let record = Record::builder()
+        .level(Level::Warn)
+        .target("estimate_size")
+        .args(format_args!("a multi-line\nmessage"))
+        .key_values(&kvs)
+        .build();
+
+    let estimate = logger.estimate_size(&record);
+    assert_eq!(estimate, logger.journal_encode(&record).len());
+    // Estimating doesn't consume a sequence number
+    assert_eq!(logger.estimate_size(&record), estimate);
+    let payload = String::from_utf8(logger.journal_encode(&record)).unwrap();
+    assert!(payload.contains("SEQ=1\n"), "{}", payload);
+    // Estimating doesn't call the error hook for the broken key-value
+    assert!(payload.contains("KV_ERRORS=1\n"), "{}", payload);
+    assert!(errors.lock().unwrap().is_empty());
+}
+
+#[test]
+fn append_kv_to_message() {
+    let kvs: &[(&str, Value)] = &[
+        ("user", Value::from("alice")),
+        ("reason", Value::from("bad password")),
+        ("attempts", Value::from(3)),
+    ];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::MESSAGE)
+        .with_append_kv_to_message(true)
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("append_kv_to_message")
+                .args(format_args!("login failed"))
+                .key_values(&kvs)
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "MESSAGE\n\x38\0\0\0\0\0\0\0login failed user=alice reason=\"bad password\" attempts=3\n\
+         ATTEMPTS\n\x01\0\0\0\0\0\0\x003\n\
+         REASON\n\x0c\0\0\0\0\0\0\0bad password\n\
+         USER\n\x05\0\0\0\0\0\0\0alice\n"
+    );
+}
+
+#[test]
+fn passthrough_fields() {
+    let kvs: &[(&str, Value)] = &[
+        ("REQUEST_ID", Value::from("42")),
+        ("user.name", Value::from("alice")),
+    ];
+    let payload = JournalLog::empty()
+        .unwrap()
+        .with_standard_fields(StandardFields::MESSAGE)
+        .with_passthrough_fields(HashSet::from(["REQUEST_ID".to_string()]))
+        .unwrap()
+        .journal_encode(
+            &Record::builder()
+                .level(Level::Info)
+                .target("passthrough_fields")
+                .args(format_args!("request"))
+                .key_values(&kvs)
+                .build(),
+        );
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "MESSAGE\n\x07\0\0\0\0\0\0\0request\n\
+         REQUEST_ID\n\x02\0\0\0\0\0\0\x0042\n\
+         USER_NAME\n\x05\0\0\0\0\0\0\0alice\n"
+    );
+
+    let result = JournalLog::empty()
+        .unwrap()
+        .with_passthrough_fields(HashSet::from(["request_id".to_string()]));
+    assert!(
+        matches!(result, Err(Error::InvalidFieldName(ref name)) if name == "request_id"),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn lean_below() {
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_syslog_identifier("lean".to_string())
+        .with_lean_below(Level::Info);
+    let encode = |level| {
+        let payload = logger.journal_encode(
+            &Record::builder()
+                .level(level)
+                .target("lean_below")
+                .module_path(Some("lean::module"))
+                .file(Some("src/lean.rs"))
+                .line(Some(7))
+                .args(format_args!("hot loop"))
+                .build(),
+        );
+        String::from_utf8(payload).unwrap()
+    };
+
+    assert_eq!(
+        encode(Level::Debug),
+        "PRIORITY=6
+MESSAGE
+\x08\0\0\0\0\0\0\0hot loop
+TARGET=lean_below
+"
+    );
+    assert_eq!(
+        encode(Level::Error),
+        format!(
+            "PRIORITY=3
+MESSAGE
+\x08\0\0\0\0\0\0\0hot loop
+SYSLOG_IDENTIFIER=lean
+SYSLOG_PID={}
+CODE_FILE=src/lean.rs
+CODE_LINE=7
+CODE_MODULE=lean::module
+TARGET=lean_below
+",
+            std::process::id()
+        )
+    );
+}
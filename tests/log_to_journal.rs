@@ -11,11 +11,17 @@
 
 #![deny(warnings, clippy::all)]
 
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::os::unix::net::UnixDatagram;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use log::kv::Value;
-use log::{Level, Log, Record};
+use log::{Level, LevelFilter, Log, Record};
 use similar_asserts::assert_eq;
 
-use systemd_journal_logger::JournalLog;
+use systemd_journal_logger::{EmptyMessagePolicy, Error, JournalContext, JournalLog, Priority};
 
 mod journal;
 
@@ -77,6 +83,23 @@ fn internal_null_byte_in_message() {
     );
 }
 
+#[test]
+fn identifier_with_special_bytes() {
+    JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("my\nservice\x07".to_string())
+        .log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("identifier_with_special_bytes")
+                .args(format_args!("odd identifier"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("identifier_with_special_bytes");
+    assert_eq!(entry["SYSLOG_IDENTIFIER"].as_text(), "my\nservice\x07");
+}
+
 #[test]
 fn multiline_message() {
     JournalLog::new().unwrap().log(
@@ -113,6 +136,36 @@ fn trailing_newline_message() {
     assert_eq!(entry["MESSAGE"], "trailing newline\n");
 }
 
+#[test]
+fn trim_trailing_newline_message() {
+    let logger = JournalLog::new().unwrap().with_trim_trailing_newline(true);
+    logger.log(
+        &Record::builder()
+            .level(Level::Trace)
+            .target("trim_trailing_newline_message")
+            .args(format_args!("trailing newline\r\n"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("trim_trailing_newline_message");
+    assert_eq!(entry["MESSAGE"], "trailing newline");
+}
+
+#[test]
+fn trim_trailing_newline_disabled() {
+    let logger = JournalLog::new().unwrap().with_trim_trailing_newline(false);
+    logger.log(
+        &Record::builder()
+            .level(Level::Trace)
+            .target("trim_trailing_newline_disabled")
+            .args(format_args!("trailing newline\n"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("trim_trailing_newline_disabled");
+    assert_eq!(entry["MESSAGE"], "trailing newline\n");
+}
+
 #[test]
 fn very_large_message() {
     let very_large_string = "b".repeat(512_000);
@@ -222,7 +275,1304 @@ fn duplicate_fields() {
 
     assert_eq!(entry["PRIORITY"], "3");
     assert_eq!(entry["MESSAGE"], "Hello world");
-    // First the field value from the record, then the one from the logger itself,
-    // since we append extra fields of the logger at the very end.
-    assert_eq!(entry["FOO"], vec!["record foo", "logger foo"]);
+    // We send the field value from the logger first, then the one from the
+    // record (see journal_encode tests), but journald stores multiple values
+    // in no particular order.
+    let mut values = match &entry["FOO"] {
+        journal::FieldValue::Array(values) => values.clone(),
+        other => panic!("Expected multiple values, got {:?}", other),
+    };
+    values.sort();
+    assert_eq!(values, vec!["logger foo", "record foo"]);
+}
+
+#[test]
+fn transport_tag() {
+    JournalLog::new().unwrap().with_transport_tag("http").log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("transport_tag")
+            .args(format_args!("with a transport tag"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("transport_tag");
+    assert_eq!(entry["MESSAGE"], "with a transport tag");
+    assert_eq!(entry["TRANSPORT"], "http");
+}
+
+#[test]
+fn transport_tag_override() {
+    let kvs: &[(&str, Value)] = &[("transport", Value::from("worker"))];
+
+    JournalLog::new().unwrap().with_transport_tag("http").log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("transport_tag_override")
+            .args(format_args!("with an overridden transport tag"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("transport_tag_override");
+    assert_eq!(entry["MESSAGE"], "with an overridden transport tag");
+    // The record overrides the tag of the logger, so there's just a single value
+    assert_eq!(entry["TRANSPORT"], "worker");
+}
+
+#[test]
+fn default_fields() {
+    JournalLog::new()
+        .unwrap()
+        .with_default_fields(vec![("COMPONENT".to_string(), "core".to_string())])
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("default_fields")
+                .args(format_args!("with a default field"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("default_fields");
+    assert_eq!(entry["MESSAGE"], "with a default field");
+    assert_eq!(entry["COMPONENT"], "core");
+}
+
+#[test]
+fn default_fields_overridden_by_record() {
+    let kvs: &[(&str, Value)] = &[("component", Value::from("network"))];
+
+    JournalLog::new()
+        .unwrap()
+        .with_default_fields(vec![("COMPONENT".to_string(), "core".to_string())])
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("default_fields_overridden_by_record")
+                .args(format_args!("with an overridden default field"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("default_fields_overridden_by_record");
+    assert_eq!(entry["MESSAGE"], "with an overridden default field");
+    // Only the value of the record, not the default
+    assert_eq!(entry["COMPONENT"], "network");
+}
+
+#[test]
+fn target_as_identifier() {
+    JournalLog::new()
+        .unwrap()
+        .with_target_as_identifier(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("target_as_identifier")
+                .args(format_args!("with the target as identifier"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("target_as_identifier");
+    assert_eq!(entry["SYSLOG_IDENTIFIER"], "target_as_identifier");
+}
+
+#[test]
+fn explicit_identifier_wins_over_target() {
+    JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("explicit".to_string())
+        .with_target_as_identifier(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("explicit_identifier_wins_over_target")
+                .args(format_args!("with an explicit identifier"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("explicit_identifier_wins_over_target");
+    assert_eq!(entry["SYSLOG_IDENTIFIER"], "explicit");
+}
+
+#[test]
+fn identifier_from_comm() {
+    JournalLog::new().unwrap().with_identifier_from_comm().log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("identifier_from_comm")
+            .args(format_args!("with the process name as identifier"))
+            .build(),
+    );
+
+    let comm = std::fs::read_to_string("/proc/self/comm").unwrap();
+    let entry = journal::read_one_entry("identifier_from_comm");
+    assert_eq!(entry["SYSLOG_IDENTIFIER"], comm.trim_end());
+}
+
+#[test]
+fn log_at() {
+    let when = SystemTime::now() - Duration::from_secs(3600);
+    JournalLog::new()
+        .unwrap()
+        .log_at(
+            when,
+            &Record::builder()
+                .level(Level::Info)
+                .target("log_at")
+                .args(format_args!("an hour ago"))
+                .build(),
+        )
+        .unwrap();
+
+    let entry = journal::read_one_entry("log_at");
+    assert_eq!(entry["MESSAGE"], "an hour ago");
+    assert_eq!(
+        entry["SOURCE_REALTIME_TIMESTAMP"],
+        when.duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros()
+            .to_string()
+    );
+    assert!(!entry["SYSLOG_TIMESTAMP"].as_text().is_empty());
+}
+
+#[test]
+fn log_with_file_field() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-file-field-{}.conf",
+        std::process::id()
+    ));
+    std::fs::write(&path, "[section]\nkey = value\n").unwrap();
+    JournalLog::new()
+        .unwrap()
+        .log_with_file_field(
+            Level::Info,
+            "log_with_file_field",
+            "configuration snapshot",
+            "config_snapshot",
+            &path,
+        )
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let entry = journal::read_one_entry("log_with_file_field");
+    assert_eq!(entry["MESSAGE"], "configuration snapshot");
+    assert_eq!(entry["CONFIG_SNAPSHOT"], "[section]\nkey = value\n");
+}
+
+#[test]
+fn log_with_binary() {
+    let packet = [0x45, 0x00, 0x00, 0x1c, 0xff, 0x0a, 0x00, 0x01];
+    JournalLog::new()
+        .unwrap()
+        .log_with_binary(
+            Level::Info,
+            "log_with_binary",
+            "captured packet",
+            "packet",
+            &packet,
+        )
+        .unwrap();
+
+    let entry = journal::read_one_entry("log_with_binary");
+    assert_eq!(entry["MESSAGE"], "captured packet");
+    assert!(entry["PACKET"] == packet[..], "{:?}", entry["PACKET"]);
+    assert_eq!(entry["PACKET_LEN"], "8");
+}
+
+#[test]
+fn log_with_missing_file_field() {
+    let error = JournalLog::new()
+        .unwrap()
+        .log_with_file_field(
+            Level::Info,
+            "log_with_missing_file_field",
+            "never sent",
+            "config_snapshot",
+            std::path::Path::new("/this/file/does/not/exist"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        std::io::ErrorKind::NotFound
+    );
+}
+
+#[test]
+fn emit() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("emit".to_string());
+    let kvs: &[(&str, Value)] = &[("foo", Value::from("bar")), ("answer", Value::from(42))];
+    logger.emit(Level::Warn, "emit", format_args!("Hello {}", "emit"), kvs);
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("emit_builder")
+            .args(format_args!("Hello {}", "emit"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let emitted = journal::read_one_entry("emit");
+    let built = journal::read_one_entry("emit_builder");
+    for field in ["PRIORITY", "MESSAGE", "SYSLOG_IDENTIFIER", "FOO", "ANSWER"] {
+        assert_eq!(emitted[field], built[field], "{}", field);
+    }
+    assert_eq!(emitted["MESSAGE"], "Hello emit");
+}
+
+#[test]
+fn log_with_context() {
+    let context = JournalContext::new()
+        .field("user", "alice")
+        .field("REQUEST", "42")
+        .priority(Priority::Critical);
+    JournalLog::new().unwrap().log_with_context(
+        Level::Info,
+        "log_with_context",
+        format_args!("with context"),
+        &context,
+    );
+
+    let entry = journal::read_one_entry("log_with_context");
+    assert_eq!(entry["MESSAGE"], "with context");
+    assert_eq!(entry["USER"], "alice");
+    assert_eq!(entry["REQUEST"], "42");
+    assert_eq!(entry["PRIORITY"], "2");
+}
+
+#[test]
+fn drop_below_when_unattached() {
+    let env_name = "_TEST_DROP_BELOW_WHEN_UNATTACHED";
+    if std::env::var_os(env_name).is_none() {
+        // Restart in a child process without JOURNAL_STREAM, to make sure standard error isn't
+        // connected to the journal, without changing the environment of other tests
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "drop_below_when_unattached", "--quiet"])
+            .env(env_name, "1")
+            .env_remove("JOURNAL_STREAM")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        return;
+    }
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_drop_below_when_unattached(Level::Warn);
+    assert!(!logger.would_log(Level::Trace, "drop_below_when_unattached"));
+    for (level, message) in [(Level::Trace, "dropped"), (Level::Error, "passed")] {
+        logger.emit(
+            level,
+            "drop_below_when_unattached",
+            format_args!("{}", message),
+            &[],
+        );
+    }
+
+    let entry = journal::read_one_entry("drop_below_when_unattached");
+    assert_eq!(entry["MESSAGE"], "passed");
+    assert_eq!(logger.diagnostics().sent, 1);
+}
+
+#[test]
+fn log_catalog() {
+    // Limits for key-values don't apply to MESSAGE_ID
+    JournalLog::new()
+        .unwrap()
+        .with_max_fields(0)
+        .with_kv_count_field(true)
+        .log_catalog(
+            Level::Info,
+            "log_catalog",
+            0xfc2e22bc6ee647b6b90729ab34a250b1,
+            format_args!("Process {} dumped core", 42),
+        );
+
+    let entry = journal::read_one_entry("log_catalog");
+    assert_eq!(entry["MESSAGE"], "Process 42 dumped core");
+    assert_eq!(entry["MESSAGE_ID"], "fc2e22bc6ee647b6b90729ab34a250b1");
+    assert_eq!(entry["KV_COUNT"], "0");
+    assert!(!entry.contains_key("FIELDS_DROPPED"), "{entry:?}");
+}
+
+#[test]
+fn log_startup() {
+    JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("log_startup_service".to_string())
+        .with_version("1.2.3")
+        .with_max_fields(0)
+        .with_kv_count_field(true)
+        .log_startup("log_startup");
+
+    let entry = journal::read_one_entry("log_startup");
+    assert_eq!(entry["KV_COUNT"], "0");
+    assert_eq!(entry["MESSAGE"], "starting log_startup_service");
+    assert_eq!(entry["PRIORITY"], "5");
+    assert_eq!(entry["PID"], std::process::id().to_string());
+    assert_eq!(entry["VERSION"], "1.2.3");
+    let args: Vec<String> = std::env::args().collect();
+    assert_eq!(entry["ARGS"], args.join(" "));
+    // RFC 3339 timestamps of the same format sort chronologically
+    let start_time = entry["START_TIME"].to_string();
+    let now = SystemTime::now();
+    assert!(
+        start_time <= systemd_journal_logger::field_datetime(now),
+        "{}",
+        start_time
+    );
+    assert!(
+        systemd_journal_logger::field_datetime(now - Duration::from_secs(3600)) < start_time,
+        "{}",
+        start_time
+    );
+}
+
+#[test]
+fn line_writer() {
+    let logger = JournalLog::new().unwrap();
+    {
+        let mut writer = logger.line_writer(Level::Warn, "line_writer".to_string());
+        writer.write_all(b"first line\nsecond").unwrap();
+        writer.write_all(b" line\npartial").unwrap();
+    }
+
+    let entries = journal::read_entries("line_writer", 3);
+    let messages: Vec<_> = entries.iter().map(|e| e["MESSAGE"].to_string()).collect();
+    assert_eq!(messages, vec!["first line", "second line", "partial"]);
+    assert!(entries.iter().all(|e| e["PRIORITY"] == "4"));
+}
+
+#[test]
+fn line_writer_keeps_failed_lines() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-line-writer-{}.socket",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Bind and immediately close the socket, to leave a dead socket file behind
+    drop(UnixDatagram::bind(&path).unwrap());
+    let logger = JournalLog::new().unwrap().with_socket_path(&path);
+    let mut writer = logger.line_writer(Level::Warn, "line_writer_keeps_failed_lines".to_string());
+
+    assert_eq!(writer.write(b"first\nsecond\nthird").unwrap(), 18);
+    let error = writer.write(b" line\n").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    assert_eq!(
+        writer.flush().unwrap_err().kind(),
+        ErrorKind::ConnectionRefused
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    let journal = UnixDatagram::bind(&path).unwrap();
+    journal
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.write(b" line\n").unwrap(), 6);
+    let mut messages = Vec::new();
+    for _ in 0..3 {
+        let mut buffer = vec![0; 4096];
+        let size = journal.recv(&mut buffer).unwrap();
+        // The logger always writes MESSAGE length-encoded
+        let start = buffer.windows(8).position(|w| w == b"MESSAGE\n").unwrap() + 8;
+        let len = u64::from_le_bytes(buffer[start..start + 8].try_into().unwrap()) as usize;
+        let message = &buffer[start + 8..start + 8 + len];
+        assert!(start + 8 + len < size);
+        messages.push(String::from_utf8(message.to_vec()).unwrap());
+    }
+    assert_eq!(messages, vec!["first", "second", "third line"]);
+    drop(writer);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn numeric_booleans() {
+    let kvs: &[(&str, Value)] = &[
+        ("spam_with_eggs", Value::from(false)),
+        ("bacon", Value::from(true)),
+    ];
+
+    JournalLog::new().unwrap().with_numeric_booleans(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("numeric_booleans")
+            .args(format_args!("with numeric booleans"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("numeric_booleans");
+    assert_eq!(entry["SPAM_WITH_EGGS"], "0");
+    assert_eq!(entry["BACON"], "1");
+}
+
+#[test]
+fn comm_field() {
+    let logger = JournalLog::new().unwrap().with_comm_field(true);
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("worker-17".to_string())
+            .spawn_scoped(scope, || {
+                logger.log(
+                    &Record::builder()
+                        .level(Level::Info)
+                        .target("comm_field")
+                        .args(format_args!("from a named thread"))
+                        .build(),
+                )
+            })
+            .unwrap();
+    });
+
+    let entry = journal::read_one_entry("comm_field");
+    assert_eq!(entry["COMM"], "worker-17");
+}
+
+#[test]
+fn concurrent_logging() {
+    let logger = std::sync::Arc::new(JournalLog::new().unwrap());
+    let threads: Vec<_> = (0..8)
+        .map(|thread| {
+            let logger = logger.clone();
+            std::thread::spawn(move || {
+                for i in 0..25 {
+                    let kvs: &[(&str, Value)] = &[("thread", Value::from(thread))];
+                    logger.log(
+                        &Record::builder()
+                            .level(Level::Info)
+                            .target("concurrent_logging")
+                            .args(format_args!("thread {} record {}", thread, i))
+                            .key_values(&kvs)
+                            .build(),
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let entries = journal::read_entries("concurrent_logging", 200);
+    let mut messages: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let message = entry["MESSAGE"].to_string();
+            assert!(message.starts_with(&format!("thread {} ", entry["THREAD"])));
+            message
+        })
+        .collect();
+    messages.sort();
+    messages.dedup();
+    assert_eq!(messages.len(), 200);
+    assert_eq!(logger.diagnostics().sent, 200);
+}
+
+#[test]
+fn thread_id_field() {
+    let logger = std::sync::Arc::new(JournalLog::new().unwrap().with_thread_id_field(true));
+    let threads: Vec<_> = (0..2)
+        .map(|thread| {
+            let logger = logger.clone();
+            std::thread::spawn(move || {
+                for i in 0..2 {
+                    let kvs: &[(&str, Value)] = &[("thread", Value::from(thread))];
+                    logger.log(
+                        &Record::builder()
+                            .level(Level::Info)
+                            .target("thread_id_field")
+                            .args(format_args!("thread {} record {}", thread, i))
+                            .key_values(&kvs)
+                            .build(),
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let entries = journal::read_entries("thread_id_field", 4);
+    let ids_of = |thread: &str| -> Vec<String> {
+        entries
+            .iter()
+            .filter(|entry| entry["THREAD"] == thread)
+            .map(|entry| entry["RUST_THREAD_ID"].to_string())
+            .collect()
+    };
+    let (first, second) = (ids_of("0"), ids_of("1"));
+    assert_eq!(first.len(), 2);
+    assert_eq!(first[0], first[1]);
+    assert_eq!(second.len(), 2);
+    assert_eq!(second[0], second[1]);
+    assert_ne!(first[0], second[0]);
+    assert!(first[0].parse::<u64>().unwrap() > 0);
+}
+
+fn log_empty_message(logger: &JournalLog, target: &str) {
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target(target)
+            .args(format_args!(""))
+            .build(),
+    );
+}
+
+#[test]
+fn empty_message_policy_send() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_empty_message_policy(EmptyMessagePolicy::Send);
+    log_empty_message(&logger, "empty_message_policy_send");
+
+    let entry = journal::read_one_entry("empty_message_policy_send");
+    assert_eq!(entry["MESSAGE"], "");
+}
+
+#[test]
+fn empty_message_policy_skip() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_empty_message_policy(EmptyMessagePolicy::Skip);
+    log_empty_message(&logger, "empty_message_policy_skip");
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("empty_message_policy_skip")
+            .args(format_args!("not empty"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("empty_message_policy_skip");
+    assert_eq!(entry["MESSAGE"], "not empty");
+}
+
+#[test]
+fn suppressed_count() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_empty_message_policy(EmptyMessagePolicy::Skip);
+    assert_eq!(logger.suppressed_count(), 0);
+    for _ in 0..3 {
+        log_empty_message(&logger, "suppressed_count");
+    }
+    assert_eq!(logger.suppressed_count(), 3);
+    assert_eq!(logger.diagnostics().dropped, 0);
+}
+
+#[test]
+fn empty_message_policy_placeholder() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_empty_message_policy(EmptyMessagePolicy::Placeholder("<empty>".to_string()));
+    log_empty_message(&logger, "empty_message_policy_placeholder");
+
+    let entry = journal::read_one_entry("empty_message_policy_placeholder");
+    assert_eq!(entry["MESSAGE"], "<empty>");
+}
+
+#[test]
+fn exe_path_field() {
+    JournalLog::new().unwrap().with_exe_path_field(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("exe_path_field")
+            .args(format_args!("with executable path"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("exe_path_field");
+    assert_eq!(
+        entry["EXE"],
+        std::env::current_exe().unwrap().to_str().unwrap()
+    );
+}
+
+#[test]
+fn kv_count_field() {
+    let kvs: &[(&str, Value)] = &[("spam", Value::from("eggs")), ("bacon", Value::from(42))];
+
+    JournalLog::new().unwrap().with_kv_count_field(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("kv_count_field")
+            .args(format_args!("with two key-values"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("kv_count_field");
+    assert_eq!(entry["KV_COUNT"], "2");
+}
+
+#[test]
+fn message_len_field() {
+    let logger = JournalLog::new().unwrap().with_message_len_field(true);
+    for message in ["Hello", "Grüße, 世界"] {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("message_len_field")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    let entries = journal::read_entries("message_len_field", 2);
+    let lens: Vec<String> = entries
+        .iter()
+        .map(|entry| entry["MESSAGE_LEN"].to_string())
+        .collect();
+    // "ü" and "ß" take two bytes each, and "世" and "界" three bytes each
+    assert_eq!(lens, vec!["5", "15"]);
+}
+
+#[test]
+fn bind_address() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-bind-address-{}.socket",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_bind_address(path.clone())
+        .unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("bind_address")
+            .args(format_args!("from a bound socket"))
+            .build(),
+    );
+
+    assert!(path.exists());
+    let entry = journal::read_one_entry("bind_address");
+    assert_eq!(entry["MESSAGE"], "from a bound socket");
+    // The logger removes its socket file, so that the next logger can bind again
+    drop(logger);
+    assert!(!path.exists());
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_bind_address(path.clone())
+        .unwrap();
+    // Closing the socket also removes the file
+    let logger = logger.with_max_level(LevelFilter::Off);
+    assert!(!path.exists());
+    logger
+        .with_max_level(LevelFilter::Trace)
+        .journal_send(
+            &Record::builder()
+                .level(Level::Info)
+                .target("bind_address")
+                .args(format_args!("from a new bound socket"))
+                .build(),
+        )
+        .unwrap();
+    assert!(!path.exists());
+    let entry = journal::read_entries("bind_address", 2).pop().unwrap();
+    assert_eq!(entry["MESSAGE"], "from a new bound socket");
+}
+
+#[test]
+fn bind_address_in_use() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-bind-address-in-use-{}.socket",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let other = UnixDatagram::bind(&path).unwrap();
+    let result = JournalLog::new().unwrap().with_bind_address(path.clone());
+    assert!(
+        matches!(result, Err(Error::Io(ref error)) if error.kind() == ErrorKind::AddrInUse),
+        "{:?}",
+        result.err()
+    );
+    drop(other);
+    std::fs::remove_file(&path).unwrap();
+
+    // Take the address while the logger has no socket, to make reopening fail
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_bind_address(path.clone())
+        .unwrap()
+        .with_max_level(LevelFilter::Off);
+    let _other = UnixDatagram::bind(&path).unwrap();
+    let result = logger.with_max_level(LevelFilter::Trace).journal_send(
+        &Record::builder()
+            .level(Level::Info)
+            .target("bind_address_in_use")
+            .args(format_args!("from an unbound socket"))
+            .build(),
+    );
+    assert!(
+        matches!(result, Err(Error::Io(ref error)) if error.kind() == ErrorKind::AddrInUse),
+        "{result:?}"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn abstract_bind_address() {
+    let name = format!(
+        "\0systemd-journal-logger-bind-address-{}",
+        std::process::id()
+    );
+    JournalLog::new()
+        .unwrap()
+        .with_bind_address(name.into())
+        .unwrap()
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("abstract_bind_address")
+                .args(format_args!("from an abstract socket"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("abstract_bind_address");
+    assert_eq!(entry["MESSAGE"], "from an abstract socket");
+}
+
+#[test]
+fn field_datetime() {
+    let created = UNIX_EPOCH + Duration::from_micros(1_696_161_600_123_456);
+    let created = systemd_journal_logger::field_datetime(created);
+    let kvs: &[(&str, Value)] = &[("created", Value::from(created.as_str()))];
+    JournalLog::new().unwrap().log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("field_datetime")
+            .args(format_args!("with a timestamp"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("field_datetime");
+    assert_eq!(entry["CREATED"], "2023-10-01T12:00:00.123456Z");
+}
+
+#[test]
+fn field_uuid() {
+    let id = systemd_journal_logger::field_uuid(0x67e55044_10b1_426f_9247_bb680e5fe0c8);
+    let kvs: &[(&str, Value)] = &[("request_id", Value::from(id.as_str()))];
+    JournalLog::new().unwrap().log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("field_uuid")
+            .args(format_args!("with a uuid"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("field_uuid");
+    assert_eq!(entry["REQUEST_ID"], "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    assert_eq!(
+        systemd_journal_logger::field_uuid(1),
+        "00000000-0000-0000-0000-000000000001"
+    );
+}
+
+#[test]
+fn field_ip_and_socketaddr() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use systemd_journal_logger::{field_ip, field_socketaddr};
+
+    let v4 = field_ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+    let v6 = field_ip(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+    let peer = field_socketaddr(SocketAddr::new(
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        443,
+    ));
+    let kvs: &[(&str, Value)] = &[
+        ("v4", Value::from(v4.as_str())),
+        ("v6", Value::from(v6.as_str())),
+        ("peer", Value::from(peer.as_str())),
+    ];
+    JournalLog::new().unwrap().log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("field_ip_and_socketaddr")
+            .args(format_args!("with addresses"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("field_ip_and_socketaddr");
+    assert_eq!(entry["V4"], "192.0.2.1");
+    assert_eq!(entry["V6"], "2001:db8::1");
+    assert_eq!(entry["PEER"], "[2001:db8::1]:443");
+    assert_eq!(
+        field_socketaddr(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80)),
+        "127.0.0.1:80"
+    );
+}
+
+#[test]
+fn max_fields() {
+    let names: Vec<String> = (0..20).map(|i| format!("field_{i:02}")).collect();
+    let kvs: Vec<(&str, Value)> = names
+        .iter()
+        .map(|name| (name.as_str(), Value::from(name.as_str())))
+        .collect();
+
+    JournalLog::new().unwrap().with_max_fields(3).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("max_fields")
+            .args(format_args!("with many key-values"))
+            .key_values(&kvs.as_slice())
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("max_fields");
+    let fields: Vec<&str> = entry
+        .keys()
+        .filter(|name| name.starts_with("FIELD_"))
+        .map(|name| name.as_str())
+        .collect();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(entry["FIELDS_DROPPED"], "17");
+    assert_eq!(entry["FIELD_00"], "field_00");
+    assert!(!entry.contains_key("FIELD_03"));
+}
+
+#[test]
+fn scoped_fields() {
+    let logger = JournalLog::new().unwrap();
+    let log = |target: &str| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target(target)
+                .args(format_args!("scoped"))
+                .build(),
+        )
+    };
+    let fields = vec![("request_id".to_string(), "4711".to_string())];
+    let result = logger.with_scoped_fields(fields.clone(), || {
+        log("scoped_fields_inside");
+        42
+    });
+    assert_eq!(result, 42);
+    log("scoped_fields_after");
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        logger.with_scoped_fields(fields, || panic!("scoped panic"))
+    }));
+    assert!(panicked.is_err());
+    log("scoped_fields_after_panic");
+
+    let entry = journal::read_one_entry("scoped_fields_inside");
+    assert_eq!(entry["REQUEST_ID"], "4711");
+    let entry = journal::read_one_entry("scoped_fields_after");
+    assert!(!entry.contains_key("REQUEST_ID"));
+    let entry = journal::read_one_entry("scoped_fields_after_panic");
+    assert!(!entry.contains_key("REQUEST_ID"));
+}
+
+#[test]
+fn nested_scoped_fields() {
+    let logger = JournalLog::new().unwrap();
+    let outer = vec![
+        ("request_id".to_string(), "4711".to_string()),
+        ("stage".to_string(), "outer".to_string()),
+    ];
+    let inner = vec![
+        ("user".to_string(), "alice".to_string()),
+        ("stage".to_string(), "inner".to_string()),
+    ];
+    logger.with_scoped_fields(outer, || {
+        logger.with_scoped_fields(inner, || {
+            logger.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("nested_scoped_fields")
+                    .args(format_args!("nested"))
+                    .build(),
+            )
+        })
+    });
+
+    let entry = journal::read_one_entry("nested_scoped_fields");
+    assert_eq!(entry["REQUEST_ID"], "4711");
+    assert_eq!(entry["USER"], "alice");
+    // The innermost scope wins, and the entry has a single value
+    assert_eq!(entry["STAGE"], "inner");
+}
+
+#[test]
+fn credential_fields() {
+    JournalLog::new().unwrap().with_credential_fields(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("credential_fields")
+            .args(format_args!("with credentials"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("credential_fields");
+    // SAFETY: geteuid and getegid always succeed.
+    let (euid, egid) = unsafe { (libc::geteuid(), libc::getegid()) };
+    assert_eq!(entry["EUID"], euid.to_string());
+    assert_eq!(entry["EGID"], egid.to_string());
+}
+
+#[test]
+fn machine_id() {
+    if !std::path::Path::new("/etc/machine-id").exists() {
+        return;
+    }
+    JournalLog::new().unwrap().with_machine_id(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("machine_id")
+            .args(format_args!("with machine id"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("machine_id");
+    let machine_id = entry["MACHINE_ID"].to_string();
+    assert_eq!(machine_id.len(), 32, "{}", machine_id);
+    assert!(machine_id.chars().all(|c| c.is_ascii_hexdigit()));
+}
+
+#[test]
+fn fd_count_field() {
+    JournalLog::new().unwrap().with_fd_count_field(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("fd_count_field")
+            .args(format_args!("with open fds"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("fd_count_field");
+    let count: usize = entry["OPEN_FDS"].to_string().parse().unwrap();
+    assert!(0 < count, "{}", count);
+}
+
+#[test]
+fn sequence_field() {
+    let logger = JournalLog::new().unwrap().with_sequence_field(true);
+    for i in 0..3 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("sequence_field")
+                .args(format_args!("record {}", i))
+                .build(),
+        );
+    }
+
+    let entries = journal::read_entries("sequence_field", 3);
+    let sequence: Vec<_> = entries.iter().map(|e| e["SEQ"].to_string()).collect();
+    assert_eq!(sequence, vec!["0", "1", "2"]);
+}
+
+#[test]
+fn severity_overrides_priority() {
+    let kvs: &[(&str, Value)] = &[("severity", Value::from("crit"))];
+    JournalLog::new().unwrap().log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("severity_overrides_priority")
+            .args(format_args!("critical"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("severity_overrides_priority");
+    assert_eq!(entry["PRIORITY"], "2");
+    assert_eq!(entry["SEVERITY"], "crit");
+}
+
+#[test]
+fn unknown_severity_uses_level() {
+    let kvs: &[(&str, Value)] = &[("severity", Value::from("catastrophic"))];
+    JournalLog::new().unwrap().log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("unknown_severity_uses_level")
+            .args(format_args!("unknown severity"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("unknown_severity_uses_level");
+    assert_eq!(entry["PRIORITY"], "4");
+}
+
+#[test]
+fn new_unchecked() {
+    let logger = JournalLog::new_unchecked();
+    logger
+        .journal_send(
+            &Record::builder()
+                .level(Level::Info)
+                .target("new_unchecked")
+                .args(format_args!("from an unchecked logger"))
+                .build(),
+        )
+        .unwrap();
+
+    let entry = journal::read_one_entry("new_unchecked");
+    assert_eq!(entry["MESSAGE"], "from an unchecked logger");
+    assert_eq!(logger.diagnostics().sent, 1);
+}
+
+#[test]
+fn git_commit() {
+    JournalLog::new()
+        .unwrap()
+        .with_git_commit("0123456789abcdef0123456789abcdef01234567")
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("git_commit")
+                .args(format_args!("with a commit"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("git_commit");
+    assert_eq!(
+        entry["GIT_COMMIT"],
+        "0123456789abcdef0123456789abcdef01234567"
+    );
+}
+
+#[test]
+fn build_profile_field() {
+    JournalLog::new()
+        .unwrap()
+        .with_build_profile_field(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("build_profile_field")
+                .args(format_args!("with a build profile"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("build_profile_field");
+    let expected = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    assert_eq!(entry["BUILD_PROFILE"], expected);
+}
+
+#[test]
+fn env_field() {
+    if std::env::var_os("SYSTEMD_JOURNAL_LOGGER_TEST_CONTAINER").is_none() {
+        // Restart in a child process with the variables, without changing the environment of
+        // other tests
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "env_field", "--quiet"])
+            .env("SYSTEMD_JOURNAL_LOGGER_TEST_CONTAINER", "web-1")
+            .env_remove("SYSTEMD_JOURNAL_LOGGER_TEST_UNSET")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        return;
+    }
+
+    JournalLog::new()
+        .unwrap()
+        .with_env_field(
+            "CONTAINER_NAME".to_string(),
+            "SYSTEMD_JOURNAL_LOGGER_TEST_CONTAINER".to_string(),
+        )
+        .with_env_field(
+            "POD_NAME".to_string(),
+            "SYSTEMD_JOURNAL_LOGGER_TEST_UNSET".to_string(),
+        )
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("env_field")
+                .args(format_args!("with an env field"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("env_field");
+    assert_eq!(entry["CONTAINER_NAME"], "web-1");
+    assert!(!entry.contains_key("POD_NAME"), "{entry:?}");
+}
+
+#[test]
+fn field_merge() {
+    let kvs: &[(&str, Value)] = &[("tag", Value::from("b"))];
+    JournalLog::new()
+        .unwrap()
+        .add_extra_field("TAG", "a")
+        .with_field_merge(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("field_merge")
+                .args(format_args!("merged"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("field_merge");
+    assert_eq!(entry["TAG"], "a,b");
+}
+
+#[test]
+fn field_merge_separator() {
+    let kvs: &[(&str, Value)] = &[("tag", Value::from("b")), ("other", Value::from("c"))];
+    JournalLog::new()
+        .unwrap()
+        .with_extra_fields(vec![("TAG", "a"), ("SPAM", "eggs")])
+        .with_field_merge_separator(" | ")
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("field_merge_separator")
+                .args(format_args!("merged"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("field_merge_separator");
+    assert_eq!(entry["TAG"], "a | b");
+    assert_eq!(entry["SPAM"], "eggs");
+    assert_eq!(entry["OTHER"], "c");
+}
+
+#[test]
+fn would_log() {
+    let logger = JournalLog::new().unwrap();
+    for level in [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ] {
+        for target in ["would_log", "other::target", ""] {
+            let metadata = log::Metadata::builder().level(level).target(target).build();
+            assert!(logger.would_log(level, target));
+            assert_eq!(logger.would_log(level, target), logger.enabled(&metadata));
+        }
+    }
+}
+
+#[test]
+fn cpu_time_field() {
+    JournalLog::new().unwrap().with_cpu_time_field(true).log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("cpu_time_field")
+            .args(format_args!("with cpu time"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("cpu_time_field");
+    // Parsing as unsigned integer asserts that the value is non-negative
+    let cpu_time: Result<u64, _> = entry["THREAD_CPU_US"].as_text().parse();
+    assert!(cpu_time.is_ok(), "{:?}", entry["THREAD_CPU_US"]);
+}
+
+#[test]
+fn size_warning_field() {
+    let logger = JournalLog::empty().unwrap().with_size_warning_field(1000);
+    let large_message = "x".repeat(2000);
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("size_warning_field_large")
+            .args(format_args!("{}", large_message))
+            .build(),
+    );
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("size_warning_field_small")
+            .args(format_args!("small"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("size_warning_field_large");
+    let size: usize = entry["ENTRY_SIZE"].as_text().parse().unwrap();
+    assert!(2000 < size, "{size}");
+    assert_eq!(entry["ENTRY_SIZE_WARN"], "1");
+    let entry = journal::read_one_entry("size_warning_field_small");
+    assert!(!entry.contains_key("ENTRY_SIZE"));
+    assert!(!entry.contains_key("ENTRY_SIZE_WARN"));
+}
+
+#[test]
+fn sampling() {
+    let logger = JournalLog::new().unwrap().with_sampling(Level::Debug, 10);
+    for i in 0..100 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Debug)
+                .target("sampling")
+                .args(format_args!("sampled record {}", i))
+                .build(),
+        );
+    }
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("sampling")
+            .args(format_args!("not sampled"))
+            .build(),
+    );
+
+    let entries = journal::read_entries("sampling", 11);
+    let sampled: Vec<_> = entries
+        .iter()
+        .filter(|e| e.contains_key("SAMPLED"))
+        .collect();
+    assert_eq!(sampled.len(), 10);
+    assert_eq!(sampled[1]["MESSAGE"], "sampled record 10");
+    assert!(sampled.iter().all(|e| e["SAMPLE_RATE"] == "10"));
+    assert_eq!(logger.suppressed_count(), 90);
+}
+
+#[test]
+fn passcred() {
+    for enabled in [true, false] {
+        let logger = JournalLog::new().unwrap().with_passcred(enabled);
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("passcred")
+                .args(format_args!("passcred {}", enabled))
+                .build(),
+        );
+        assert_eq!(logger.diagnostics().passcred, Some(enabled));
+    }
+
+    let entries = journal::read_entries("passcred", 2);
+    let messages: Vec<String> = entries
+        .iter()
+        .map(|entry| entry["MESSAGE"].to_string())
+        .collect();
+    assert_eq!(messages, vec!["passcred true", "passcred false"]);
+    for entry in entries {
+        // journald trusts the kernel's credentials either way
+        assert_eq!(entry["_PID"], std::process::id().to_string());
+        assert_eq!(entry["SYSLOG_PID"], entry["_PID"]);
+        assert_eq!(entry["_UID"], unsafe { libc::getuid() }.to_string());
+    }
 }
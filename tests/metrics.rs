@@ -0,0 +1,115 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test the counters of the metrics feature.
+
+#![deny(warnings, clippy::all)]
+#![cfg(feature = "metrics")]
+
+use std::collections::BTreeMap;
+use std::os::unix::net::UnixDatagram;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{Level, Log, Record};
+use metrics::{
+    Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+/// A recorder which only captures counters.
+#[derive(Default)]
+struct CaptureCounters(Mutex<BTreeMap<String, Arc<AtomicU64>>>);
+
+struct CapturedCounter(Arc<AtomicU64>);
+
+impl CounterFn for CapturedCounter {
+    fn increment(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+impl CaptureCounters {
+    fn counters(&self) -> BTreeMap<String, u64> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl Recorder for CaptureCounters {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        let value = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(key.name().to_string())
+            .or_default()
+            .clone();
+        Counter::from_arc(Arc::new(CapturedCounter(value)))
+    }
+
+    fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[test]
+fn sent_and_dropped_counters() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-metrics-{}.socket",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Bind and immediately close the socket, to leave a dead socket file behind
+    drop(UnixDatagram::bind(&path).unwrap());
+
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("sent_and_dropped_counters")
+        .args(format_args!("counted"))
+        .build();
+    let recorder = CaptureCounters::default();
+    metrics::with_local_recorder(&recorder, || {
+        let logger = JournalLog::new().unwrap();
+        logger.log(&record);
+        logger.log(&record);
+        JournalLog::new()
+            .unwrap()
+            .with_socket_path(&path)
+            .log(&record);
+    });
+
+    assert_eq!(
+        recorder.counters(),
+        BTreeMap::from([
+            ("journal_logger_dropped_total".to_string(), 1),
+            ("journal_logger_sent_total".to_string(), 2),
+        ])
+    );
+    std::fs::remove_file(&path).unwrap();
+}
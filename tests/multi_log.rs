@@ -0,0 +1,53 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test that records go to the journal and to other loggers.
+
+#![deny(warnings, clippy::all)]
+
+use std::sync::{Arc, Mutex};
+
+use log::{Level, Log, Metadata, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{JournalLog, MultiLog};
+
+mod journal;
+
+/// A logger which keeps the messages of all records in memory.
+#[derive(Clone, Default)]
+struct MemoryLog(Arc<Mutex<Vec<String>>>);
+
+impl Log for MemoryLog {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn multi_log() {
+    let memory = MemoryLog::default();
+    let logger = MultiLog::new(JournalLog::new().unwrap()).with_logger(Box::new(memory.clone()));
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("multi_log")
+            .args(format_args!("Hello fan-out"))
+            .build(),
+    );
+
+    assert_eq!(*memory.0.lock().unwrap(), vec!["Hello fan-out"]);
+    let entry = journal::read_one_entry("multi_log");
+    assert_eq!(entry["MESSAGE"], "Hello fan-out");
+}
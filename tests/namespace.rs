@@ -0,0 +1,124 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test routing records to namespaces with fake journal sockets.
+
+#![deny(warnings, clippy::all)]
+
+use std::os::unix::net::UnixDatagram;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{Error, JournalLog};
+
+/// Bind a fake journal socket at `path`.
+fn fake_journal(path: &std::path::Path) -> UnixDatagram {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let socket = UnixDatagram::bind(path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    socket
+}
+
+fn receive(socket: &UnixDatagram) -> String {
+    let mut buffer = vec![0; 4096];
+    let size = socket.recv(&mut buffer).unwrap();
+    String::from_utf8_lossy(&buffer[..size]).into_owned()
+}
+
+#[test]
+fn namespace_router() {
+    let directory = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-namespace-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&directory);
+    let default = fake_journal(&directory.join("journal").join("socket"));
+    let errors = fake_journal(&directory.join("journal.errors").join("socket"));
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_path(directory.join("journal").join("socket"))
+        .with_namespace_router(|level| (level == Level::Error).then(|| "errors".to_string()));
+    for (level, message) in [
+        (Level::Error, "to errors"),
+        (Level::Info, "to default"),
+        (Level::Error, "to errors again"),
+    ] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("namespace_router")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    let first = receive(&errors);
+    assert!(first.contains("to errors"), "{first:?}");
+    let second = receive(&errors);
+    assert!(second.contains("to errors again"), "{second:?}");
+    let info = receive(&default);
+    assert!(info.contains("to default"), "{info:?}");
+    assert!(info.starts_with("PRIORITY=5\n"), "{info:?}");
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn namespace_router_invalid_namespace() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_namespace_router(|_| Some("../journal".to_string()));
+    let result = logger.journal_send(
+        &Record::builder()
+            .level(Level::Error)
+            .target("namespace_router_invalid_namespace")
+            .args(format_args!("nowhere"))
+            .build(),
+    );
+    assert!(
+        matches!(result, Err(Error::InvalidNamespace(ref name)) if name == "../journal"),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn namespace_router_invalid_namespace_calls_error_hook() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let logger = {
+        let errors = errors.clone();
+        JournalLog::new()
+            .unwrap()
+            .with_namespace_router(|_| Some("../journal".to_string()))
+            .on_error(move |error| errors.lock().unwrap().push(error.to_string()))
+    };
+    let record = Record::builder()
+        .level(Level::Error)
+        .target("namespace_router_invalid_namespace_calls_error_hook")
+        .args(format_args!("nowhere"))
+        .build();
+    let result = logger.journal_send(&record);
+    assert!(
+        matches!(result, Err(Error::InvalidNamespace(ref name)) if name == "../journal"),
+        "{result:?}"
+    );
+    logger.log(&record);
+
+    assert_eq!(
+        *errors.lock().unwrap(),
+        vec![
+            "../journal is not a valid journal namespace".to_string(),
+            "../journal is not a valid journal namespace".to_string()
+        ]
+    );
+}
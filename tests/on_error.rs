@@ -0,0 +1,81 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test the error hook with a socket nobody listens on.
+
+#![deny(warnings, clippy::all)]
+
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+/// A socket path which exists but has no listener.
+fn dead_socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-{}-{}.socket",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Bind and immediately close the socket, to leave a dead socket file behind
+    drop(UnixDatagram::bind(&path).unwrap());
+    path
+}
+
+#[test]
+fn on_error_called_for_failed_send() {
+    let path = dead_socket_path("on_error");
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let logger = {
+        let errors = errors.clone();
+        JournalLog::new()
+            .unwrap()
+            .with_socket_path(&path)
+            .on_error(move |error| errors.lock().unwrap().push(error.kind()))
+    };
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .target("on_error_called_for_failed_send")
+            .args(format_args!("lost"))
+            .build(),
+    );
+
+    assert_eq!(
+        *errors.lock().unwrap(),
+        vec![std::io::ErrorKind::ConnectionRefused]
+    );
+    assert_eq!(logger.diagnostics().socket_path, path);
+    assert_eq!(logger.diagnostics().dropped, 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn on_error_panics_are_caught() {
+    let path = dead_socket_path("on_error_panics");
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_path(&path)
+        .on_error(|_| panic!("faulty hook"));
+
+    let record = Record::builder()
+        .level(Level::Error)
+        .target("on_error_panics_are_caught")
+        .args(format_args!("lost"))
+        .build();
+    // Neither of these may panic
+    logger.log(&record);
+    assert!(logger.journal_send(&record).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,70 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test OpenTelemetry span context fields.
+
+#![deny(warnings, clippy::all)]
+#![cfg(feature = "otel")]
+
+use log::{Level, Log, Record};
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry::Context;
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+mod journal;
+
+#[test]
+fn span_context_fields() {
+    let span_context = SpanContext::new(
+        TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+        SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    let _guard = Context::current()
+        .with_remote_span_context(span_context)
+        .attach();
+
+    JournalLog::new()
+        .unwrap()
+        .with_current_span_context(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("span_context_fields")
+                .args(format_args!("within a span"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("span_context_fields");
+    assert_eq!(entry["MESSAGE"], "within a span");
+    assert_eq!(entry["TRACE_ID"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(entry["SPAN_ID"], "00f067aa0ba902b7");
+}
+
+#[test]
+fn no_span_context_fields_without_span() {
+    JournalLog::new()
+        .unwrap()
+        .with_current_span_context(true)
+        .log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("no_span_context_fields_without_span")
+                .args(format_args!("outside of a span"))
+                .build(),
+        );
+
+    let entry = journal::read_one_entry("no_span_context_fields_without_span");
+    assert_eq!(entry["MESSAGE"], "outside of a span");
+    assert!(!entry.contains_key("TRACE_ID"));
+    assert!(!entry.contains_key("SPAN_ID"));
+}
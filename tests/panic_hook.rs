@@ -0,0 +1,38 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![deny(warnings, clippy::all)]
+
+mod journal;
+
+use similar_asserts::assert_eq;
+use systemd_journal_logger::JournalLog;
+
+#[test]
+fn install_panic_hook() {
+    JournalLog::new()
+        .unwrap()
+        .with_extra_fields(vec![("SPAM", "EGGS")])
+        .install_panic_hook(false)
+        .unwrap();
+
+    let line = line!() + 2;
+    let result = std::thread::spawn(|| {
+        panic!("Oh no: {}", 42);
+    })
+    .join();
+    assert!(result.is_err());
+
+    let entry = journal::read_one_entry("panic");
+    assert_eq!(entry["PRIORITY"], "2");
+    assert_eq!(entry["MESSAGE"], "Oh no: 42");
+    assert_eq!(entry["CODE_FILE"], file!());
+    assert_eq!(entry["CODE_LINE"], line.to_string());
+    assert_eq!(entry["SPAM"], "EGGS");
+    assert!(entry["BACKTRACE"].as_text().contains("panic_hook"));
+}
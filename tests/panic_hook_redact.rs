@@ -0,0 +1,54 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test that the panic hook redacts fields.
+//!
+//! The panic hook is global, so this test needs its own test binary.
+
+#![deny(warnings, clippy::all)]
+
+use std::sync::{Arc, Mutex};
+
+use systemd_journal_logger::{JournalLog, JournalTransport};
+
+/// A transport which captures all entries in memory.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl JournalTransport for Capture {
+    fn send(&self, data: &[u8]) -> std::io::Result<()> {
+        self.0.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn panic_hook_redacts_fields() {
+    let capture = Capture::default();
+    JournalLog::new_unchecked()
+        .with_transport(capture.clone())
+        .with_field_redactor(|_, value| {
+            let value = String::from_utf8_lossy(value);
+            Some(value.replace("hunter2", "****").into_bytes())
+        })
+        .install_panic_hook(false)
+        .unwrap();
+
+    let result = std::thread::spawn(|| panic!("login failed with password hunter2")).join();
+    assert!(result.is_err());
+
+    let entries = capture.0.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    let entry = String::from_utf8_lossy(&entries[0]);
+    assert!(
+        entry.contains("MESSAGE\n\x1f\0\0\0\0\0\0\0login failed with password ****\n"),
+        "{}",
+        entry
+    );
+    assert!(!entry.contains("hunter2"), "{}", entry);
+}
@@ -0,0 +1,53 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test the send timeout with a listener which never reads.
+
+#![deny(warnings, clippy::all)]
+
+use std::os::unix::net::UnixDatagram;
+use std::time::{Duration, Instant};
+
+use log::{Level, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+#[test]
+fn send_times_out() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-send-timeout-{}.socket",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    // Keep the listener open, but never read from it, so its queue fills up
+    let listener = UnixDatagram::bind(&path).unwrap();
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_path(&path)
+        .with_send_timeout(Duration::from_millis(50));
+
+    let record = Record::builder()
+        .level(Level::Info)
+        .target("send_times_out")
+        .args(format_args!("never read"))
+        .build();
+    let start = Instant::now();
+    let error = (0..100_000)
+        .find_map(|_| logger.journal_send(&record).err())
+        .expect("Sends never blocked");
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(logger.diagnostics().dropped, 1);
+
+    drop(listener);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,94 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test logging serializable values.
+
+#![deny(warnings, clippy::all)]
+#![cfg(feature = "serde")]
+
+use std::sync::{Arc, Mutex};
+
+use log::kv::Value;
+use log::{Level, Log, Record};
+use serde::Serialize;
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+mod journal;
+
+#[derive(Serialize)]
+struct Peer {
+    address: &'static str,
+    port: u16,
+}
+
+#[derive(Serialize)]
+struct Request {
+    method: &'static str,
+    status_code: u16,
+    cached: bool,
+    peer: Peer,
+}
+
+#[test]
+fn log_with_serde() {
+    JournalLog::new()
+        .unwrap()
+        .log_with_serde(
+            Level::Info,
+            "log_with_serde",
+            format_args!("Request done"),
+            &Request {
+                method: "GET",
+                status_code: 200,
+                cached: false,
+                peer: Peer {
+                    address: "127.0.0.1",
+                    port: 8080,
+                },
+            },
+        )
+        .unwrap();
+
+    let entry = journal::read_one_entry("log_with_serde");
+    assert_eq!(entry["MESSAGE"], "Request done");
+    assert_eq!(entry["METHOD"], "GET");
+    assert_eq!(entry["STATUS_CODE"], "200");
+    assert_eq!(entry["CACHED"], "false");
+    assert_eq!(entry["PEER"], r#"{"address":"127.0.0.1","port":8080}"#);
+}
+
+#[test]
+fn json_tee() {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let logger = JournalLog::new().unwrap().with_json_tee(buffer.clone());
+    let kvs: &[(&str, Value)] = &[("user_id", Value::from(42)), ("path", Value::from("/"))];
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("json_tee")
+            .args(format_args!("Hello {}", "JSON"))
+            .key_values(&kvs)
+            .build(),
+    );
+
+    let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+    assert!(output.ends_with('\n'), "{output:?}");
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "priority": 4,
+            "message": "Hello JSON",
+            "fields": {"USER_ID": "42", "PATH": "/"},
+        })
+    );
+    let entry = journal::read_one_entry("json_tee");
+    assert_eq!(entry["MESSAGE"], "Hello JSON");
+}
@@ -0,0 +1,122 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test sending through a seqpacket socket.
+
+#![deny(warnings, clippy::all)]
+
+use std::ffi::CString;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{JournalLog, SocketType};
+
+mod journal;
+
+/// Bind a listening `SOCK_SEQPACKET` socket at `path`.
+fn seqpacket_listener(path: &Path) -> OwnedFd {
+    let _ = std::fs::remove_file(path);
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    // SAFETY: We check all results, and only pass a properly initialized address.
+    unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0);
+        assert!(0 <= fd, "{}", std::io::Error::last_os_error());
+        let fd = OwnedFd::from_raw_fd(fd);
+        let mut addr: libc::sockaddr_un = std::mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in addr.sun_path.iter_mut().zip(path.as_bytes()) {
+            *dst = *src as libc::c_char;
+        }
+        let result = libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+        );
+        assert_eq!(result, 0, "{}", std::io::Error::last_os_error());
+        assert_eq!(libc::listen(fd.as_raw_fd(), 1), 0);
+        fd
+    }
+}
+
+/// Accept a connection on `listener` and receive one packet.
+fn receive_packet(listener: &OwnedFd) -> Vec<u8> {
+    let mut buffer = vec![0; 4096];
+    // SAFETY: We check all results, and pass a buffer with its proper size.
+    unsafe {
+        let fd = libc::accept(
+            listener.as_raw_fd(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        assert!(0 <= fd, "{}", std::io::Error::last_os_error());
+        let connection = OwnedFd::from_raw_fd(fd);
+        let received = libc::recv(
+            connection.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            buffer.len(),
+            0,
+        );
+        assert!(0 <= received, "{}", std::io::Error::last_os_error());
+        buffer.truncate(received as usize);
+    }
+    buffer
+}
+
+#[test]
+fn seqpacket_delivery() {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-seqpacket-{}.socket",
+        std::process::id()
+    ));
+    let listener = seqpacket_listener(&path);
+    let logger = JournalLog::empty()
+        .unwrap()
+        .with_socket_path(&path)
+        .with_socket_type(SocketType::SeqPacket);
+    assert_eq!(logger.diagnostics().socket_type, SocketType::SeqPacket);
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("seqpacket_delivery")
+            .args(format_args!("Hello seqpacket"))
+            .build(),
+    );
+
+    let payload = String::from_utf8(receive_packet(&listener)).unwrap();
+    assert!(payload.contains("Hello seqpacket\n"), "{payload:?}");
+    assert!(
+        payload.contains("TARGET=seqpacket_delivery\n"),
+        "{payload:?}"
+    );
+    assert_eq!(logger.diagnostics().sent, 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn seqpacket_falls_back_to_datagram() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_type(SocketType::SeqPacket);
+    assert_eq!(logger.diagnostics().socket_type, SocketType::Datagram);
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("seqpacket_falls_back_to_datagram")
+            .args(format_args!("Hello datagram"))
+            .build(),
+    );
+
+    let entry = journal::read_one_entry("seqpacket_falls_back_to_datagram");
+    assert_eq!(entry["MESSAGE"], "Hello datagram");
+}
@@ -0,0 +1,132 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test the syslog fallback with a journal socket nobody listens on.
+
+#![deny(warnings, clippy::all)]
+
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{Delivery, EmptyMessagePolicy, JournalLog};
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "systemd-journal-logger-{}-{}.socket",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn syslog_fallback() {
+    let journal_path = socket_path("syslog-fallback-journal");
+    let syslog_path = socket_path("syslog-fallback-syslog");
+    let syslog = UnixDatagram::bind(&syslog_path).unwrap();
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_syslog_identifier("fallback".to_string())
+        .with_socket_path(&journal_path)
+        .with_syslog_fallback(true)
+        .with_syslog_socket_path(&syslog_path);
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("syslog_fallback")
+            .args(format_args!("Hello syslog"))
+            .build(),
+    );
+
+    let mut buffer = vec![0; 1024];
+    let size = syslog.recv(&mut buffer).unwrap();
+    let message = String::from_utf8(buffer[..size].to_vec()).unwrap();
+    // <12> is the user facility with warning severity
+    assert!(message.starts_with("<12>"), "{message:?}");
+    assert!(
+        message.ends_with(&format!(" fallback[{}]: Hello syslog", std::process::id())),
+        "{message:?}"
+    );
+    assert_eq!(logger.diagnostics().dropped, 1);
+    std::fs::remove_file(&syslog_path).unwrap();
+}
+
+#[test]
+fn syslog_fallback_delivery() {
+    let journal_path = socket_path("syslog-fallback-delivery-journal");
+    let syslog_path = socket_path("syslog-fallback-delivery-syslog");
+    let syslog = UnixDatagram::bind(&syslog_path).unwrap();
+    let record = Record::builder()
+        .level(Level::Warn)
+        .target("syslog_fallback_delivery")
+        .args(format_args!("Hello syslog"))
+        .build();
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_syslog_fallback(true)
+        .with_syslog_socket_path(&syslog_path)
+        .with_empty_message_policy(EmptyMessagePolicy::Skip);
+    assert_eq!(logger.journal_send(&record).unwrap(), Delivery::Journal);
+    assert_eq!(
+        logger
+            .journal_send(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target("syslog_fallback_delivery")
+                    .args(format_args!(""))
+                    .build()
+            )
+            .unwrap(),
+        Delivery::Discarded
+    );
+
+    let logger = logger.with_socket_path(&journal_path);
+    assert_eq!(logger.journal_send(&record).unwrap(), Delivery::Syslog);
+    let mut buffer = vec![0; 1024];
+    let size = syslog.recv(&mut buffer).unwrap();
+    let message = String::from_utf8(buffer[..size].to_vec()).unwrap();
+    assert!(message.ends_with(": Hello syslog"), "{message:?}");
+    std::fs::remove_file(&syslog_path).unwrap();
+}
+
+#[test]
+fn syslog_fallback_disabled() {
+    let journal_path = socket_path("syslog-fallback-disabled-journal");
+    let syslog_path = socket_path("syslog-fallback-disabled-syslog");
+    let syslog = UnixDatagram::bind(&syslog_path).unwrap();
+    syslog.set_nonblocking(true).unwrap();
+
+    let logger = JournalLog::new()
+        .unwrap()
+        .with_socket_path(&journal_path)
+        .with_syslog_socket_path(&syslog_path);
+    let result = logger.journal_send(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("syslog_fallback_disabled")
+            .args(format_args!("Hello syslog"))
+            .build(),
+    );
+
+    assert_eq!(
+        result.unwrap_err().io_error().unwrap().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    let mut buffer = vec![0; 1024];
+    assert_eq!(
+        syslog.recv(&mut buffer).unwrap_err().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+    std::fs::remove_file(&syslog_path).unwrap();
+}
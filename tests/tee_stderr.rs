@@ -0,0 +1,62 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test that records go to the journal and to standard error.
+//!
+//! The test restarts itself as a child process to capture standard error,
+//! and then checks the captured output and the journal.
+
+#![deny(warnings, clippy::all)]
+
+use std::process::Command;
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::JournalLog;
+
+mod journal;
+
+#[test]
+fn tee_stderr() {
+    let env_name = "_TEST_TEE_STDERR_TARGET";
+    match std::env::var(env_name) {
+        Ok(target) => {
+            JournalLog::new().unwrap().with_tee_stderr(true).log(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target(&target)
+                    .args(format_args!("Hello stderr"))
+                    .build(),
+            );
+        }
+        Err(_) => {
+            let target = format!("tee_stderr_{}", std::process::id());
+            let output = Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "tee_stderr", "--quiet"])
+                .env(env_name, &target)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            // Standard error isn't a terminal here, so we get no colors.
+            assert_eq!(
+                String::from_utf8(output.stderr).unwrap(),
+                format!("WARN  {}: Hello stderr\n", target)
+            );
+
+            let entry = retry::retry(retry::delay::Fixed::from_millis(100).take(30), || {
+                let mut entries =
+                    journal::read(journal::Journal::User, [format!("TARGET={}", target)]);
+                entries.pop().ok_or("No entries")
+            })
+            .unwrap();
+            assert_eq!(entry["MESSAGE"], "Hello stderr");
+            assert_eq!(entry["PRIORITY"], "4");
+        }
+    }
+}
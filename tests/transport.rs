@@ -0,0 +1,179 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test sending entries through custom transports.
+
+#![deny(warnings, clippy::all)]
+
+use std::sync::{Arc, Mutex};
+
+use log::{Level, Log, Record};
+use similar_asserts::assert_eq;
+
+use systemd_journal_logger::{JournalLog, JournalTransport, StandardFields};
+
+/// A transport which captures all entries in memory.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl JournalTransport for Capture {
+    fn send(&self, data: &[u8]) -> std::io::Result<()> {
+        self.0.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+}
+
+/// An entry with the namespace it was sent to, if any.
+type NamespacedEntry = (Option<String>, Vec<u8>);
+
+/// A transport which captures entries with their namespace.
+#[derive(Clone, Default)]
+struct CaptureNamespaces(Arc<Mutex<Vec<NamespacedEntry>>>);
+
+impl JournalTransport for CaptureNamespaces {
+    fn send(&self, data: &[u8]) -> std::io::Result<()> {
+        self.0.lock().unwrap().push((None, data.to_vec()));
+        Ok(())
+    }
+
+    fn send_to_namespace(&self, namespace: &str, data: &[u8]) -> std::io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push((Some(namespace.to_string()), data.to_vec()));
+        Ok(())
+    }
+}
+
+/// A transport which always fails.
+struct Broken;
+
+impl JournalTransport for Broken {
+    fn send(&self, _data: &[u8]) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::BrokenPipe,
+            "broken transport",
+        ))
+    }
+}
+
+#[test]
+fn custom_transport_captures_entries() {
+    let capture = Capture::default();
+    let logger = JournalLog::new_unchecked()
+        .with_standard_fields(StandardFields::PRIORITY | StandardFields::MESSAGE)
+        .with_transport(capture.clone());
+
+    let record = Record::builder()
+        .level(Level::Warn)
+        .target("custom_transport_captures_entries")
+        .args(format_args!("captured"))
+        .build();
+    logger.log(&record);
+
+    assert_eq!(
+        *capture.0.lock().unwrap(),
+        vec![b"PRIORITY=4\nMESSAGE\n\x08\0\0\0\0\0\0\0captured\n".to_vec()]
+    );
+    assert_eq!(logger.journal_encode(&record), capture.0.lock().unwrap()[0]);
+    assert_eq!(logger.diagnostics().sent, 1);
+    assert_eq!(logger.diagnostics().dropped, 0);
+}
+
+#[test]
+fn custom_transport_errors() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let logger = {
+        let errors = errors.clone();
+        JournalLog::new_unchecked()
+            .with_transport(Broken)
+            .on_error(move |error| errors.lock().unwrap().push(error.kind()))
+    };
+
+    let record = Record::builder()
+        .level(Level::Error)
+        .target("custom_transport_errors")
+        .args(format_args!("lost"))
+        .build();
+    let error = logger.journal_send(&record).unwrap_err();
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        std::io::ErrorKind::BrokenPipe
+    );
+    assert_eq!(
+        *errors.lock().unwrap(),
+        vec![std::io::ErrorKind::BrokenPipe]
+    );
+    assert_eq!(logger.diagnostics().sent, 0);
+    assert_eq!(logger.diagnostics().dropped, 1);
+}
+
+#[test]
+fn custom_transport_namespaces() {
+    let capture = CaptureNamespaces::default();
+    let logger = JournalLog::new_unchecked()
+        .with_standard_fields(StandardFields::MESSAGE)
+        .with_namespace_router(|level| (level == Level::Debug).then(|| "debug".to_string()))
+        .with_transport(capture.clone());
+
+    for (level, message) in [(Level::Debug, "routed"), (Level::Info, "default")] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("custom_transport_namespaces")
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    assert_eq!(
+        *capture.0.lock().unwrap(),
+        vec![
+            (
+                Some("debug".to_string()),
+                b"MESSAGE\n\x06\0\0\0\0\0\0\0routed\n".to_vec()
+            ),
+            (None, b"MESSAGE\n\x07\0\0\0\0\0\0\0default\n".to_vec())
+        ]
+    );
+    assert_eq!(logger.diagnostics().sent, 2);
+}
+
+#[test]
+fn custom_transport_panics() {
+    let capture = Capture::default();
+    let logger = JournalLog::new_unchecked()
+        .with_transport(capture.clone())
+        .with_syslog_identifier("before".to_string());
+    logger.install_panic_hook(true).unwrap();
+    // The panic hook follows the configuration of the logger
+    let mut config = logger.config();
+    config.syslog_identifier = "after".to_string();
+    config.extra_fields = vec![("SPAM".to_string(), b"eggs".to_vec())];
+    config.standard_fields = StandardFields::all() - StandardFields::CODE_LINE;
+    config.target_field_name = "COMPONENT".to_string();
+    logger.reconfigure(config);
+
+    let result = std::thread::spawn(|| panic!("captured panic")).join();
+    assert!(result.is_err());
+
+    let entries = capture.0.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    let entry = String::from_utf8_lossy(&entries[0]);
+    assert!(entry.starts_with("PRIORITY=2\n"), "{}", entry);
+    assert!(
+        entry.contains("MESSAGE\n\x0e\0\0\0\0\0\0\0captured panic\n"),
+        "{}",
+        entry
+    );
+    assert!(entry.contains("SYSLOG_IDENTIFIER=after\n"), "{}", entry);
+    assert!(entry.contains("COMPONENT=panic\n"), "{}", entry);
+    assert!(entry.ends_with("SPAM=eggs\n"), "{}", entry);
+    assert!(!entry.contains("TARGET="), "{}", entry);
+    assert!(!entry.contains("CODE_LINE="), "{}", entry);
+}
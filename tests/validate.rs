@@ -0,0 +1,87 @@
+// Copyright Sebastian Wiesner <sebastian@swsnr.de>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Test validating the configuration of a logger.
+
+#![deny(warnings, clippy::all)]
+
+use log::Level;
+
+use systemd_journal_logger::{Error, JournalLog};
+
+#[test]
+fn validate_valid() {
+    let logger = JournalLog::new()
+        .unwrap()
+        .add_extra_field("FOO", "foo")
+        .with_default_fields(vec![("BAR".to_string(), "bar".to_string())])
+        .with_env_field("CONTAINER_NAME".to_string(), "CONTAINER".to_string())
+        .with_namespace_router(|level| (level == Level::Error).then(|| "errors".to_string()));
+    let result = logger.validate();
+    assert!(result.is_ok(), "{result:?}");
+}
+
+#[test]
+fn validate_invalid_extra_field() {
+    let result = JournalLog::new()
+        .unwrap()
+        .add_extra_field("foo", "foo")
+        .validate();
+    assert!(
+        matches!(result, Err(Error::InvalidFieldName(ref name)) if name == "foo"),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn validate_invalid_default_field() {
+    let result = JournalLog::new()
+        .unwrap()
+        .with_default_fields(vec![("_BAR".to_string(), "bar".to_string())])
+        .validate();
+    assert!(
+        matches!(result, Err(Error::InvalidFieldName(ref name)) if name == "_BAR"),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn validate_invalid_env_field() {
+    let result = JournalLog::new()
+        .unwrap()
+        .with_env_field("CONTAINER-NAME".to_string(), "CONTAINER".to_string())
+        .validate();
+    assert!(
+        matches!(result, Err(Error::InvalidFieldName(ref name)) if name == "CONTAINER-NAME"),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn validate_missing_socket() {
+    let result = JournalLog::new()
+        .unwrap()
+        .with_socket_path("/does/not/exist/socket")
+        .validate();
+    assert!(
+        matches!(result, Err(Error::SocketUnavailable(ref error)) if error.kind() == std::io::ErrorKind::NotFound),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn validate_invalid_namespace() {
+    let result = JournalLog::new()
+        .unwrap()
+        .with_namespace_router(|level| (level == Level::Trace).then(|| "a/b".to_string()))
+        .validate();
+    assert!(
+        matches!(result, Err(Error::InvalidNamespace(ref name)) if name == "a/b"),
+        "{result:?}"
+    );
+}